@@ -0,0 +1,15 @@
+use actix_web::{HttpResponse, ResponseError};
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use derive_more::{Display, Error};
+
+#[derive(Debug, Display, Error)]
+#[display(fmt = "Form wasn't sent from a dashboard page, reload the page and try again.")]
+pub struct CsrfError;
+impl ResponseError for CsrfError {
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(StatusCode::FORBIDDEN)
+            .insert_header(ContentType::json())
+            .body(self.to_string())
+    }
+}
@@ -0,0 +1,17 @@
+use actix_web::{HttpResponse, ResponseError};
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use derive_more::{Display, Error};
+
+#[derive(Debug, Display, Error)]
+#[display(fmt = "Device {} is busy with another operation.", device_id)]
+pub struct DeviceBusyError {
+    pub device_id: u32,
+}
+impl ResponseError for DeviceBusyError {
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(StatusCode::LOCKED)
+            .insert_header(ContentType::json())
+            .body(self.to_string())
+    }
+}
@@ -0,0 +1,19 @@
+use actix_web::{HttpResponse, ResponseError};
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use derive_more::{Display, Error};
+
+use crate::handlers::auth_handler::model::Role;
+
+#[derive(Debug, Display, Error)]
+#[display(fmt = "This needs the {:?} role.", required)]
+pub struct ForbiddenError {
+    pub required: Role,
+}
+impl ResponseError for ForbiddenError {
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(StatusCode::FORBIDDEN)
+            .insert_header(ContentType::json())
+            .body(self.to_string())
+    }
+}
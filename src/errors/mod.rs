@@ -1,2 +1,5 @@
 pub mod execution_error;
-
+pub mod device_busy_error;
+pub mod unauthorized_error;
+pub mod forbidden_error;
+pub mod csrf_error;
@@ -0,0 +1,17 @@
+use actix_web::{HttpResponse, ResponseError};
+use actix_web::http::header::{ContentType, WWW_AUTHENTICATE};
+use actix_web::http::StatusCode;
+use derive_more::{Display, Error};
+
+#[derive(Debug, Display, Error)]
+pub struct UnauthorizedError {
+    pub message: String,
+}
+impl ResponseError for UnauthorizedError {
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(StatusCode::UNAUTHORIZED)
+            .insert_header(ContentType::json())
+            .insert_header((WWW_AUTHENTICATE, "Bearer"))
+            .body(self.to_string())
+    }
+}
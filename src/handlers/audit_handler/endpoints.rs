@@ -0,0 +1,33 @@
+use actix_web::web;
+use actix_web::http::Method;
+use actix_web::web::{Data, Json, Query};
+
+use crate::handlers::deprecated;
+use crate::errors::execution_error::ExecutionError;
+use crate::handlers::audit_handler::function::{DEFAULT_LIMIT, MAX_LIMIT};
+use crate::handlers::audit_handler::model::{AuditEntry, AuditQuery};
+use crate::handlers::auth_handler::model::Admin;
+use crate::handlers::network_devices_handler::model::NetworkDevicesHandler;
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    tag = "audit",
+    params(AuditQuery),
+    responses((status = 200, body = Vec<AuditEntry>), (status = 403)),
+)]
+async fn get_audit(_admin: Admin, query: Query<AuditQuery>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Json<Vec<AuditEntry>>, ExecutionError> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let entries = network_devices_handler.audit()?.entries(query.device, query.since, limit)?;
+    Ok(Json(entries))
+}
+
+/// Routes below `/api/v1`.
+pub fn init_ah_endpoints(cfg: &mut web::ServiceConfig) {
+    cfg.route("/audit", web::get().to(get_audit));
+}
+
+/// Unversioned routes from before `/api/v1`, kept working for existing clients.
+pub fn init_ah_legacy_endpoints(cfg: &mut web::ServiceConfig) {
+    cfg.service(deprecated("/audit", Method::GET, get_audit));
+}
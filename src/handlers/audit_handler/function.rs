@@ -0,0 +1,170 @@
+use std::fmt::{Display, Formatter};
+use std::fs::{self, OpenOptions};
+use std::future::{ready, Ready};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use actix_web::{dev, FromRequest, HttpMessage, HttpRequest};
+use chrono::{DateTime, Utc};
+
+use super::model::*;
+use crate::errors::execution_error::ExecutionError;
+use crate::handlers::auth_handler::model::Identity;
+use crate::objects::config::function::ios_errors;
+
+const LOG_FILE: &str = "audit.jsonl";
+const MAX_BYTES: u64 = 10 * 1024 * 1024;
+const KEEP: usize = 5;
+/// Caller of the operations the agent runs on its own, like the refresh at startup.
+const AGENT_CALLER: &str = "agent";
+/// Entries `GET /audit` returns without a `limit`, and the most it returns with one.
+pub(crate) const DEFAULT_LIMIT: usize = 1000;
+pub(crate) const MAX_LIMIT: usize = 10000;
+
+impl AuditHandler {
+    pub fn new(audit_loc: &str) -> Self {
+        AuditHandler {
+            audit_loc: PathBuf::from(audit_loc),
+            max_bytes: MAX_BYTES,
+            keep: KEEP,
+            write_lock: Default::default(),
+        }
+    }
+
+    /// Rotates the log once it exceeds `max_bytes`, keeping `keep` rotated files.
+    pub fn with_rotation(mut self, max_bytes: u64, keep: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self.keep = keep;
+        self
+    }
+
+    pub fn record(&self, entry: &AuditEntry) -> Result<(), ExecutionError> {
+        let mut line = serde_json::to_string(entry)
+            .map_err(|why| ExecutionError { message: format!("Couldn't serialize audit entry: {}", why) })?;
+        line.push('\n');
+
+        let _guard = self.write_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        fs::create_dir_all(&self.audit_loc).map_err(|why| audit_error(&self.audit_loc, why))?;
+        let path = self.file(0);
+        let size = fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > self.max_bytes {
+            self.rotate().map_err(|why| audit_error(&path, why))?;
+        }
+        OpenOptions::new().create(true).append(true).open(&path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|why| audit_error(&path, why))
+    }
+
+    /// The newest `limit` entries oldest first, including the rotated files. Older files are
+    /// only read while there are fewer entries than that.
+    pub fn entries(&self, device: Option<u32>, since: Option<DateTime<Utc>>, limit: usize) -> Result<Vec<AuditEntry>, ExecutionError> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut entries = Vec::new();
+        for index in 0..=self.keep {
+            if entries.len() >= limit {
+                break;
+            }
+            let path = self.file(index);
+            let content = match fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(why) if why.kind() == io::ErrorKind::NotFound => continue,
+                Err(why) => return Err(audit_error(&path, why)),
+            };
+            entries.extend(content.lines()
+                .rev()
+                .filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok())
+                .filter(|entry| device.is_none_or(|device| entry.device_id == device))
+                .filter(|entry| since.is_none_or(|since| entry.timestamp >= since))
+                .take(limit - entries.len())
+                .collect::<Vec<_>>());
+        }
+        entries.reverse();
+        Ok(entries)
+    }
+
+    fn rotate(&self) -> io::Result<()> {
+        let oldest = self.file(self.keep);
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for index in (0..self.keep).rev() {
+            let path = self.file(index);
+            if path.exists() {
+                fs::rename(path, self.file(index + 1))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `audit.jsonl` for 0, the rotated files after it.
+    fn file(&self, index: usize) -> PathBuf {
+        match index {
+            0 => self.audit_loc.join(LOG_FILE),
+            _ => self.audit_loc.join(format!("{}.{}", LOG_FILE, index)),
+        }
+    }
+}
+
+impl AuditSession {
+    /// Records one console exchange, failures to write the log are only printed.
+    pub fn record(&self, hostname: &str, command: &str, result: &io::Result<String>, duration: Duration) {
+        let (response, outcome, error) = match result {
+            Ok(response) => match ios_errors(response).into_iter().next() {
+                Some(error) => (response.clone(), AuditOutcome::IosError, Some(error)),
+                None => (response.clone(), AuditOutcome::Ok, None),
+            },
+            Err(why) => (String::new(), AuditOutcome::ConsoleError, Some(why.to_string())),
+        };
+        let entry = AuditEntry {
+            timestamp: Utc::now(),
+            caller: self.origin.caller.clone(),
+            endpoint: self.origin.endpoint.clone(),
+            device_id: self.device_id,
+            hostname: hostname.to_string(),
+            commands: command.lines().map(String::from).collect(),
+            response,
+            duration_ms: duration.as_millis() as u64,
+            outcome,
+            error,
+        };
+        if let Err(why) = self.log.record(&entry) {
+            println!("Couldn't record audit entry: {}", why);
+        }
+    }
+}
+
+impl Origin {
+    /// Operations the agent starts by itself rather than for an API call.
+    pub fn agent(endpoint: &str) -> Self {
+        Origin {
+            caller: AGENT_CALLER.to_string(),
+            endpoint: endpoint.to_string(),
+        }
+    }
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} by {}", self.endpoint, self.caller)
+    }
+}
+
+impl FromRequest for Origin {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut dev::Payload) -> Self::Future {
+        let caller = match req.extensions().get::<Identity>() {
+            Some(identity) => identity.name.clone(),
+            None => req.connection_info().realip_remote_addr().unwrap_or("unknown").to_string(),
+        };
+        ready(Ok(Origin {
+            caller,
+            endpoint: format!("{} {}", req.method(), req.uri()),
+        }))
+    }
+}
+
+fn audit_error(path: &Path, why: io::Error) -> ExecutionError {
+    ExecutionError { message: format!("Couldn't access audit log {}: {}", path.display(), why) }
+}
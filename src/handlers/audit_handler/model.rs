@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Log of every command sent to the devices, appended to `audit_loc/audit.jsonl` and rotated
+/// to `audit.jsonl.1` .. `audit.jsonl.{keep}` once it grows past `max_bytes`.
+#[derive(Debug, Clone)]
+pub struct AuditHandler {
+    pub(crate) audit_loc: PathBuf,
+    pub(crate) max_bytes: u64,
+    pub(crate) keep: usize,
+    /// Device workers record concurrently but append to the same file.
+    pub(crate) write_lock: Arc<Mutex<()>>,
+}
+
+/// Who made the API call a device operation runs for and through which endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Origin {
+    pub caller: String,
+    pub endpoint: String,
+}
+
+/// Set on a device while it runs a job, so every command it sends is recorded for `origin`.
+#[derive(Debug, Clone)]
+pub struct AuditSession {
+    pub(crate) log: AuditHandler,
+    pub(crate) device_id: u32,
+    pub(crate) origin: Origin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Ok,
+    /// The device answered with an IOS error message.
+    IosError,
+    /// The console exchange itself failed.
+    ConsoleError,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub caller: String,
+    pub endpoint: String,
+    pub device_id: u32,
+    pub hostname: String,
+    /// CLI lines sent in one exchange, in order.
+    pub commands: Vec<String>,
+    pub response: String,
+    pub duration_ms: u64,
+    pub outcome: AuditOutcome,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    pub device: Option<u32>,
+    /// Only entries recorded at or after this RFC 3339 time.
+    pub since: Option<DateTime<Utc>>,
+    /// Returns only this many of the newest matching entries, 1000 by default and 10000 at most.
+    pub limit: Option<usize>,
+}
@@ -0,0 +1,216 @@
+use std::fs;
+use std::future::{ready, Ready};
+use std::io;
+use std::time::{Duration, Instant};
+use actix_web::dev::{Payload, ServiceRequest};
+use actix_web::http::header::{AUTHORIZATION, ORIGIN, SEC_WEBSOCKET_PROTOCOL};
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::model::*;
+use crate::handlers::config_handler::function::AUTH_VAR;
+use crate::errors::csrf_error::CsrfError;
+use crate::errors::forbidden_error::ForbiddenError;
+use crate::errors::unauthorized_error::UnauthorizedError;
+use crate::tls::ClientCertificate;
+
+/// Reachable without a token, so monitoring, the API docs and the dashboard login don't need one.
+const PUBLIC_PATHS: [&str; 3] = ["/status/health", "/openapi.json", LOGIN_PATH];
+const PUBLIC_PREFIXES: [&str; 1] = ["/swagger-ui/"];
+/// Identity of clients authenticated with a certificate from `tls_client_ca_loc`.
+const CONTROLLER: &str = "controller";
+/// WebSocket subprotocol announcing that the next offered one is the bearer token.
+pub const BEARER_PROTOCOL: &str = "bearer";
+/// Dashboard pages authenticate with the session cookie instead of a bearer token.
+const DASHBOARD_PREFIX: &str = "/ui";
+pub const LOGIN_PATH: &str = "/ui/login";
+pub const SESSION_COOKIE: &str = "rpi_session";
+pub const SESSION_TTL: Duration = Duration::from_secs(8 * 60 * 60);
+
+impl AuthHandler {
+    /// Accepts only `tokens`, an empty list locks everyone out.
+    pub fn new(tokens: Vec<ApiToken>) -> Self {
+        AuthHandler { enabled: true, tokens, sessions: Default::default(), csrf_token: random_token() }
+    }
+
+    /// Lets every request through with full rights.
+    pub fn disabled() -> Self {
+        AuthHandler { enabled: false, tokens: Vec::new(), sessions: Default::default(), csrf_token: random_token() }
+    }
+
+    /// Reads the tokens from the JSON list at `path`, which has to exist.
+    pub fn load(path: &str) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(AuthHandler::new(serde_json::from_str(&content)?)),
+            Err(why) if why.kind() == io::ErrorKind::NotFound => Err(io::Error::new(why.kind(),
+                format!("No API tokens at {}, create them or turn authentication off with {}=false", path, AUTH_VAR))),
+            Err(why) => Err(why),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Checks the bearer token of `req` and stores the matching `Identity` in its extensions.
+    pub fn authenticate(&self, req: &ServiceRequest) -> Result<(), UnauthorizedError> {
+        if req.conn_data::<ClientCertificate>().is_some() {
+            req.extensions_mut().insert(Identity { name: CONTROLLER.to_string(), role: Role::Admin });
+            return Ok(());
+        }
+        if !self.is_enabled() || is_public(req.path()) {
+            return Ok(());
+        }
+        let token = req.headers().get(AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .or_else(|| websocket_token(req.request()));
+        let identity = match token {
+            Some(token) => self.identify(token.trim())
+                .ok_or(UnauthorizedError { message: "Invalid bearer token.".to_string() })?,
+            None if is_dashboard(req.path()) => {
+                let session = req.cookie(SESSION_COOKIE)
+                    .and_then(|cookie| self.session(cookie.value()))
+                    .ok_or(UnauthorizedError { message: "Not logged in.".to_string() })?;
+                let identity = session.identity.clone();
+                req.extensions_mut().insert(session);
+                identity
+            }
+            None => return Err(UnauthorizedError { message: "Missing bearer token.".to_string() }),
+        };
+        req.extensions_mut().insert(identity);
+        Ok(())
+    }
+
+    /// Starts a dashboard session for `token`, returns the session id for the cookie.
+    pub fn login(&self, token: &str) -> Option<String> {
+        let identity = self.identify(token.trim())?;
+        let id = random_token();
+        let mut sessions = self.sessions.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        sessions.retain(|_, session| session.expires > now);
+        sessions.insert(id.clone(), DashboardSession { identity, csrf_token: random_token(), expires: now + SESSION_TTL });
+        Some(id)
+    }
+
+    /// Token the dashboard forms of `req` have to send back. `None` when the request came with
+    /// a bearer token or certificate, which other sites can't make a browser send.
+    pub fn csrf_token(&self, req: &HttpRequest) -> Option<String> {
+        if let Some(session) = req.extensions().get::<DashboardSession>() {
+            return Some(session.csrf_token.clone());
+        }
+        (!self.enabled).then(|| self.csrf_token.clone())
+    }
+
+    /// Refuses dashboard forms posted from other sites: the `Origin` has to be the agent and the
+    /// form has to carry the page's CSRF token.
+    pub fn check_csrf(&self, req: &HttpRequest, sent: &str) -> Result<(), CsrfError> {
+        let host = req.connection_info().host().to_string();
+        let origin = req.headers().get(ORIGIN).and_then(|origin| origin.to_str().ok());
+        if origin.is_some_and(|origin| origin.split_once("://").map(|(_, origin_host)| origin_host) != Some(host.as_str())) {
+            return Err(CsrfError);
+        }
+        match self.csrf_token(req) {
+            Some(expected) if !constant_time_eq(expected.as_bytes(), sent.as_bytes()) => Err(CsrfError),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn logout(&self, session_id: &str) {
+        self.sessions.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(session_id);
+    }
+
+    fn session(&self, session_id: &str) -> Option<DashboardSession> {
+        let sessions = self.sessions.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        sessions.get(session_id)
+            .filter(|session| session.expires > Instant::now())
+            .cloned()
+    }
+
+    pub(crate) fn identify(&self, token: &str) -> Option<Identity> {
+        let hash = hash_token(token);
+        self.tokens.iter()
+            .find(|known| constant_time_eq(known.hash.to_lowercase().as_bytes(), hash.as_bytes()))
+            .map(|known| Identity { name: known.name.clone(), role: known.role })
+    }
+}
+
+impl ApiToken {
+    /// Entry for `token`, as it would be written to the tokens file.
+    pub fn new(name: &str, token: &str, role: Role) -> Self {
+        ApiToken {
+            name: name.to_string(),
+            hash: hash_token(token),
+            role,
+        }
+    }
+}
+
+impl FromRequest for Operator {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(require(req, Role::Operator).map(|_| Operator).map_err(Into::into))
+    }
+}
+
+impl FromRequest for Admin {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(require(req, Role::Admin).map(|_| Admin).map_err(Into::into))
+    }
+}
+
+/// Passes when the request was authenticated with at least `role` or authentication is off.
+fn require(req: &HttpRequest, role: Role) -> Result<(), ForbiddenError> {
+    let enabled = req.app_data::<Data<AuthHandler>>().is_some_and(|auth| auth.is_enabled());
+    match req.extensions().get::<Identity>() {
+        Some(identity) if identity.role >= role => Ok(()),
+        None if !enabled => Ok(()),
+        _ => Err(ForbiddenError { required: role }),
+    }
+}
+
+/// Browsers can't set headers on WebSocket requests, so they offer the `bearer` subprotocol
+/// followed by the token as a second one instead.
+fn websocket_token(req: &HttpRequest) -> Option<&str> {
+    let mut protocols = req.headers().get(SEC_WEBSOCKET_PROTOCOL)?
+        .to_str().ok()?
+        .split(',')
+        .map(str::trim);
+    protocols.any(|protocol| protocol == BEARER_PROTOCOL)
+        .then(|| protocols.next())
+        .flatten()
+}
+
+/// Whether the WebSocket request authenticated with the token in its subprotocols, in which
+/// case the handshake has to accept `bearer` for browsers to keep the connection.
+pub fn offers_bearer_protocol(req: &HttpRequest) -> bool {
+    websocket_token(req).is_some()
+}
+
+pub fn is_dashboard(path: &str) -> bool {
+    path.strip_prefix(DASHBOARD_PREFIX).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+fn is_public(path: &str) -> bool {
+    PUBLIC_PATHS.contains(&path) || PUBLIC_PREFIXES.iter().any(|prefix| path.starts_with(prefix))
+}
+
+/// 244 random bits, hex encoded.
+fn random_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+
+/// Checks the bearer tokens of API calls. Authentication is only off when turned off
+/// explicitly, with `AuthHandler::disabled` or `RPI_AUTH=false`.
+#[derive(Debug, Clone)]
+pub struct AuthHandler {
+    pub(crate) enabled: bool,
+    pub(crate) tokens: Vec<ApiToken>,
+    /// Dashboard logins by session id, browsers can't send bearer tokens with links and forms.
+    pub(crate) sessions: Arc<Mutex<HashMap<String, DashboardSession>>>,
+    /// CSRF token of the dashboard forms while authentication is off and there are no sessions.
+    pub(crate) csrf_token: String,
+}
+
+/// Identity a browser logged in as on `/ui/login`, the session id is kept in a cookie.
+#[derive(Debug, Clone)]
+pub struct DashboardSession {
+    pub(crate) identity: Identity,
+    /// Sent back with every dashboard form, other sites can't read it from the pages.
+    pub(crate) csrf_token: String,
+    pub(crate) expires: Instant,
+}
+
+/// Entry of the tokens file, only the SHA-256 of the token is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub name: String,
+    /// Hex encoded SHA-256 of the token, e.g. from `printf %s "$TOKEN" | sha256sum`.
+    pub hash: String,
+    #[serde(default)]
+    pub role: Role,
+}
+
+/// What a token may do, each role includes the ones before it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Reads device state.
+    #[default]
+    Viewer,
+    /// Changes hostnames, VLANs and interfaces and saves the config.
+    Operator,
+    /// Restores whole configs and uses the device consoles.
+    Admin,
+}
+
+/// Token that authenticated the request, stored in the request extensions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    pub name: String,
+    pub role: Role,
+}
+
+/// Extractor that only succeeds for operators and admins, or when authentication is off.
+#[derive(Debug)]
+pub struct Operator;
+
+/// Extractor that only succeeds for admins, or when authentication is off.
+#[derive(Debug)]
+pub struct Admin;
@@ -0,0 +1,75 @@
+use actix_web::{web, HttpResponse};
+use actix_web::http::Method;
+use actix_web::web::{Data, Json, Query};
+
+use crate::handlers::deprecated;
+use crate::errors::execution_error::ExecutionError;
+use crate::handlers::backup_handler::model::{BackupDiffQuery, BackupQuery, ConfigVersion};
+use crate::handlers::network_devices_handler::model::NetworkDevicesHandler;
+use crate::objects::config::function::section_diff;
+use crate::objects::config::model::ParsedConfig;
+
+#[utoipa::path(
+    get,
+    path = "/devices/{id}/backups",
+    tag = "backups",
+    params(("id" = u32, Path, description = "Device id"), BackupQuery),
+    responses((status = 200, body = Vec<ConfigVersion>)),
+)]
+async fn list_backups(path: web::Path<u32>, query: Query<BackupQuery>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Json<Vec<ConfigVersion>>, ExecutionError> {
+    let id = path.into_inner();
+    let device = network_devices_handler.get_device(id)?;
+    let versions = network_devices_handler.backups()?.versions(&device, query.kind.as_deref())?;
+    Ok(Json(versions))
+}
+
+#[utoipa::path(
+    get,
+    path = "/devices/{id}/backups/diff",
+    tag = "backups",
+    params(("id" = u32, Path, description = "Device id"), BackupDiffQuery),
+    responses(
+        (status = 200, description = "Unified diff as JSON, or text with format=text", body = String),
+    ),
+)]
+async fn diff_backups(path: web::Path<u32>, query: Query<BackupDiffQuery>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<HttpResponse, ExecutionError> {
+    let id = path.into_inner();
+    let device = network_devices_handler.get_device(id)?;
+    let backups = network_devices_handler.backups()?;
+    let from = ParsedConfig::parse(&backups.read(&device, &query.from)?);
+    let to = ParsedConfig::parse(&backups.read(&device, &query.to)?);
+    let diff = section_diff(&from, &query.from, &to, &query.to);
+
+    if query.format.as_deref() == Some("text") {
+        return Ok(HttpResponse::Ok().content_type("text/x-diff; charset=utf-8").body(diff));
+    }
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "from": query.from, "to": query.to, "diff": diff })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/devices/{id}/backups/{version}",
+    tag = "backups",
+    params(("id" = u32, Path, description = "Device id"), ("version" = String, Path)),
+    responses((status = 200, body = String, content_type = "text/plain")),
+)]
+async fn get_backup(path: web::Path<(u32, String)>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<HttpResponse, ExecutionError> {
+    let (id, version) = path.into_inner();
+    let device = network_devices_handler.get_device(id)?;
+    let config = network_devices_handler.backups()?.read(&device, &version)?;
+    Ok(HttpResponse::Ok().content_type("text/plain; charset=utf-8").body(config))
+}
+
+/// Routes below `/api/v1`.
+pub fn init_bh_endpoints(cfg: &mut web::ServiceConfig) {
+    cfg.route("/devices/{id}/backups", web::get().to(list_backups));
+    cfg.route("/devices/{id}/backups/diff", web::get().to(diff_backups));
+    cfg.route("/devices/{id}/backups/{version}", web::get().to(get_backup));
+}
+
+/// Unversioned routes from before `/api/v1`, kept working for existing clients.
+pub fn init_bh_legacy_endpoints(cfg: &mut web::ServiceConfig) {
+    cfg.service(deprecated("/device/{id}/backups", Method::GET, list_backups));
+    cfg.service(deprecated("/device/{id}/backups/diff", Method::GET, diff_backups));
+    cfg.service(deprecated("/device/{id}/backups/{version}", Method::GET, get_backup));
+}
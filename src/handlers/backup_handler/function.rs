@@ -0,0 +1,204 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use chrono::{NaiveDateTime, Utc};
+use sha2::{Digest, Sha256};
+
+use super::model::*;
+use crate::errors::execution_error::ExecutionError;
+use crate::objects::device::model::NetworkDevice;
+
+const HASH_PREFIX_LEN: usize = 12;
+const EXTENSION: &str = "cfg";
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+const HISTORY_DIR: &str = "history";
+const GIT_AUTHOR: [&str; 4] = ["-c", "user.name=rpi_client", "-c", "user.email=rpi_client@localhost"];
+
+impl BackupHandler {
+    pub fn new(backup_loc: &str) -> Self {
+        BackupHandler {
+            backup_loc: PathBuf::from(backup_loc),
+            git: false,
+            git_lock: Default::default(),
+        }
+    }
+
+    pub fn with_git(mut self, git: bool) -> Self {
+        self.git = git;
+        self
+    }
+
+    /// Archives the device's running and startup config, skipping the ones equal to their latest
+    /// version. `trigger` names the API call that read the configs and ends up in the git history.
+    pub fn store_device(&self, device: &NetworkDevice, trigger: &str) -> Result<Vec<ConfigVersion>, ExecutionError> {
+        let mut stored = Vec::new();
+        for (kind, config) in [("running", &device.running_config), ("startup", &device.startup_config)] {
+            if config.is_empty() {
+                continue;
+            }
+            if let Some(version) = self.store(device, kind, config)? {
+                stored.push(version);
+            }
+        }
+        if self.git {
+            // the history may lag behind the archive, e.g. when git was turned on later
+            self.commit_history(device, trigger)?;
+        }
+        Ok(stored)
+    }
+
+    /// Writes the configs that differ from `{device}-{kind}.cfg` in the history repository there
+    /// and commits them.
+    fn commit_history(&self, device: &NetworkDevice, trigger: &str) -> Result<(), ExecutionError> {
+        let _guard = self.git_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let repo = self.backup_loc.join(HISTORY_DIR);
+        if !repo.join(".git").exists() {
+            fs::create_dir_all(&repo).map_err(|why| archive_error(&repo, why))?;
+            git(&repo, &["init", "--quiet"])?;
+        }
+
+        let key = device_key(device);
+        let mut files = Vec::new();
+        let mut kinds = Vec::new();
+        let mut versions = Vec::new();
+        for (kind, config) in [("running", &device.running_config), ("startup", &device.startup_config)] {
+            let file = format!("{}-{}.{}", key, kind, EXTENSION);
+            let path = repo.join(&file);
+            if config.is_empty() || fs::read_to_string(&path).is_ok_and(|tracked| tracked == *config) {
+                continue;
+            }
+            fs::write(&path, config).map_err(|why| archive_error(&path, why))?;
+            files.push(file);
+            kinds.push(kind);
+            versions.extend(self.versions(device, Some(kind))?.pop().map(|version| version.id));
+        }
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        let mut add = vec!["add", "--"];
+        add.extend(files.iter().map(|file| file.as_str()));
+        git(&repo, &add)?;
+        if git(&repo, &["diff", "--cached", "--quiet"]).is_ok() {
+            // same content as the last commit, e.g. after the plain archive was cleaned up
+            return Ok(());
+        }
+
+        let message = format!("{} ({}): {} config changed\n\nTriggered by {}\nVersions: {}",
+                              device.hostname, key, kinds.join(" and "), trigger, versions.join(", "));
+        let mut commit = GIT_AUTHOR.to_vec();
+        commit.extend(["commit", "--quiet", "-m", &message]);
+        git(&repo, &commit)
+    }
+
+    /// Stores `config` as a new version unless it's identical to the latest one of the same kind.
+    pub fn store(&self, device: &NetworkDevice, kind: &str, config: &str) -> Result<Option<ConfigVersion>, ExecutionError> {
+        let hash = format!("{:x}", Sha256::digest(config.as_bytes()));
+        let versions = self.versions(device, Some(kind))?;
+        if versions.last().is_some_and(|latest| hash.starts_with(&latest.hash)) {
+            return Ok(None);
+        }
+
+        let dir = self.device_dir(device);
+        fs::create_dir_all(&dir).map_err(|why| archive_error(&dir, why))?;
+        let timestamp = Utc::now().format(TIMESTAMP_FORMAT).to_string();
+        let id = format!("{}-{}-{}", kind, timestamp, &hash[..HASH_PREFIX_LEN]);
+        let path = dir.join(format!("{}.{}", id, EXTENSION));
+        fs::write(&path, config).map_err(|why| archive_error(&path, why))?;
+
+        Ok(Some(ConfigVersion {
+            id,
+            kind: kind.to_string(),
+            timestamp,
+            hash: hash[..HASH_PREFIX_LEN].to_string(),
+        }))
+    }
+
+    /// Versions stored for the device, oldest first.
+    pub fn versions(&self, device: &NetworkDevice, kind: Option<&str>) -> Result<Vec<ConfigVersion>, ExecutionError> {
+        let dir = self.device_dir(device);
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(why) if why.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(why) => return Err(archive_error(&dir, why)),
+        };
+
+        let mut versions: Vec<ConfigVersion> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != EXTENSION {
+                    return None;
+                }
+                parse_version(path.file_stem()?.to_str()?)
+            })
+            .filter(|version| kind.is_none_or(|kind| version.kind == kind))
+            .collect();
+        versions.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        Ok(versions)
+    }
+
+    pub fn read(&self, device: &NetworkDevice, version_id: &str) -> Result<String, ExecutionError> {
+        if parse_version(version_id).is_none() {
+            return Err(ExecutionError { message: format!("{} is not a config version.", version_id) });
+        }
+        let path = self.device_dir(device).join(format!("{}.{}", version_id, EXTENSION));
+        fs::read_to_string(&path).map_err(|why| archive_error(&path, why))
+    }
+
+    /// Directory of the device, named after its serial number so renaming the device keeps its history.
+    pub fn device_dir(&self, device: &NetworkDevice) -> PathBuf {
+        self.backup_loc.join(device_key(device))
+    }
+}
+
+/// Serial number of the device, or its hostname when the serial is unknown, safe to use as a file name.
+pub fn device_key(device: &NetworkDevice) -> String {
+    let key = if device.serial_number.is_empty() { &device.hostname } else { &device.serial_number };
+    let key: String = key.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if key.is_empty() { "unknown".to_string() } else { key }
+}
+
+fn parse_version(id: &str) -> Option<ConfigVersion> {
+    let mut parts = id.splitn(3, '-');
+    let kind = parts.next()?;
+    let timestamp = parts.next()?;
+    let hash = parts.next()?;
+    if !matches!(kind, "running" | "startup") || !is_timestamp(timestamp) || hash.len() != HASH_PREFIX_LEN || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(ConfigVersion {
+        id: id.to_string(),
+        kind: kind.to_string(),
+        timestamp: timestamp.to_string(),
+        hash: hash.to_string(),
+    })
+}
+
+/// Only timestamps exactly as `archive` writes them, so version ids stay plain file names.
+fn is_timestamp(timestamp: &str) -> bool {
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .is_ok_and(|parsed| parsed.format(TIMESTAMP_FORMAT).to_string() == timestamp)
+}
+
+/// Runs git in `repo`, failing on a non zero exit code.
+fn git(repo: &Path, args: &[&str]) -> Result<(), ExecutionError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|why| ExecutionError { message: format!("Couldn't run git: {}", why) })?;
+    if !output.status.success() {
+        return Err(ExecutionError {
+            message: format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim())
+        });
+    }
+    Ok(())
+}
+
+fn archive_error(path: &Path, why: std::io::Error) -> ExecutionError {
+    ExecutionError { message: format!("Couldn't access config archive {}: {}", path.display(), why) }
+}
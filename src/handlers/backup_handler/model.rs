@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Archive of every config read from the devices, one directory per device under `backup_loc`.
+#[derive(Debug, Clone)]
+pub struct BackupHandler {
+    pub(crate) backup_loc: PathBuf,
+    /// Also commit changed configs to a git repository in `backup_loc/history`.
+    pub(crate) git: bool,
+    /// Device workers archive concurrently but share the git index.
+    pub(crate) git_lock: Arc<Mutex<()>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ConfigVersion {
+    /// `{kind}-{timestamp}-{hash prefix}`, also the file name without extension.
+    pub id: String,
+    pub kind: String,
+    pub timestamp: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BackupQuery {
+    /// Only list versions of this kind, `running` or `startup`.
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BackupDiffQuery {
+    pub from: String,
+    pub to: String,
+    pub format: Option<String>,
+}
@@ -1,15 +1,23 @@
-use actix_web::{get, Responder, web};
+use actix_web::{Responder, web};
 use actix_web::error::JsonPayloadError;
+use actix_web::http::Method;
 use actix_web::web::{Data, Json};
 use log::info;
+use crate::handlers::deprecated;
 use crate::handlers::config_handler::model::ConfigHandler;
 
-#[get("/config")]
+#[utoipa::path(get, path = "/config", tag = "agent", responses((status = 200, body = ConfigHandler)))]
 async fn system(config: Data<ConfigHandler>) -> impl Responder{
     info!("Fetched config: {}", &config.to_string());
     Ok::<Json<Data<ConfigHandler>>,JsonPayloadError>(Json(config))
 }
 
+/// Routes below `/api/v1`.
 pub fn init_ch_endpoints(cfg: &mut web::ServiceConfig) {
-    cfg.service(system);
-}
\ No newline at end of file
+    cfg.route("/config", web::get().to(system));
+}
+
+/// Unversioned routes from before `/api/v1`, kept working for existing clients.
+pub fn init_ch_legacy_endpoints(cfg: &mut web::ServiceConfig) {
+    cfg.service(deprecated("/config", Method::GET, system));
+}
@@ -1,45 +1,97 @@
 use std::fmt::{Display, Formatter, write};
-use log::info;
+use std::env;
+use std::fs;
+use std::io;
+use log::{error, info, warn};
 use sysinfo::{NetworkExt, NetworksExt, System, SystemExt};
-use uuid::Uuid;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::{Builder, Uuid};
 
 use super::model::ConfigHandler;
+use crate::handlers::terminal_handler::model::TerminalMode;
+
+const MACHINE_ID_LOC: &str = "/etc/machine-id";
+/// Keys the agent id so it doesn't give away the machine-id, like systemd's app-specific ids.
+const AGENT_ID_APP: &[u8] = b"rpi_client agent id";
+const PORT_VAR: &str = "RPI_PORT";
+const BIND_ADDRESSES_VAR: &str = "RPI_BIND_ADDRESSES";
+const BACKUP_GIT_VAR: &str = "RPI_BACKUP_GIT";
+const TLS_VAR: &str = "RPI_TLS";
+const TLS_CLIENT_CA_VAR: &str = "RPI_TLS_CLIENT_CA";
+const TERMINAL_VAR: &str = "RPI_TERMINAL";
+pub(crate) const AUTH_VAR: &str = "RPI_AUTH";
+const TERMINAL_BIND_ADDRESSES_VAR: &str = "RPI_TERMINAL_BIND_ADDRESSES";
+const TERMINAL_BASE_PORT_VAR: &str = "RPI_TERMINAL_BASE_PORT";
+const TERMINAL_MODE_VAR: &str = "RPI_TERMINAL_MODE";
 
 impl ConfigHandler {
-    pub fn init(&self) -> Self {
+    /// Fills in what the environment and the platform say, `self` provides the defaults.
+    /// Fails on environment variables that are set to something invalid.
+    pub fn init(&self) -> io::Result<Self> {
 
         let mut sys = System::new_all();
         sys.refresh_all();
         let mut templates_loc = "./src/templates".to_string();
+        let mut config_templates_loc = "./src/config_templates".to_string();
         let mut address = "127.0.0.1".to_string();
         let mut interface_name = "vEthernet (Default Switch)";
-        let networks = sys.networks();
 
-        let mac_address = networks.iter()
-            .filter(|(&ref name, &ref data)| name.eq(interface_name))
-            .map(|(&ref name, &ref data)| data.mac_address().to_string())
+        if let Some(x) = sys.name() {
+            if x.eq("Raspberry Pi") {
+                templates_loc = "./templates".to_string();
+                config_templates_loc = "./config_templates".to_string();
+                address = "10.0.10.5".to_string();
+                interface_name = "eth0";
+            }
+        }
+
+        let mac_address = sys.networks().iter()
+            .filter(|(name, _data)| name.as_str().eq(interface_name))
+            .map(|(_name, data)| data.mac_address().to_string())
             .collect();
         info!("Mac address: {}", mac_address);
 
-        match sys.name() {
-            Some(x) => {
-                if x.eq("Raspberry Pi") {
-                    templates_loc = "./templates".to_string();
-                    address = "10.0.10.5".to_string();
-                    interface_name = "eth0";
-                }
-            }
-            None => {}
-        }
+        let port = env_port(PORT_VAR)?.unwrap_or(self.port);
+        let bind_addresses = env_addresses(BIND_ADDRESSES_VAR).unwrap_or_else(|| vec![address.clone()]);
+        let terminal_bind_addresses = env_addresses(TERMINAL_BIND_ADDRESSES_VAR).unwrap_or_else(|| self.terminal_bind_addresses.clone());
+
+        let terminal_base_port = env_port(TERMINAL_BASE_PORT_VAR)?.unwrap_or(self.terminal_base_port);
+        let terminal_mode = match env::var(TERMINAL_MODE_VAR).as_deref() {
+            Ok("raw") => TerminalMode::Raw,
+            Ok("telnet") => TerminalMode::Telnet,
+            Ok(mode) => return Err(invalid_var(TERMINAL_MODE_VAR, format!("has to be raw or telnet, not {}", mode))),
+            Err(_) => self.terminal_mode
+        };
+
+        let uuid_loc = self.uuid_loc.clone();
         let config:ConfigHandler = ConfigHandler {
-            uuid: uuid::Uuid::new_v4().to_string(),
+            uuid: load_or_create_uuid(&uuid_loc),
             ip_address: address,
+            bind_addresses,
+            port,
             platform: sys.name().expect("Couldn't get OS name."),
             templates_loc,
+            config_templates_loc,
+            uuid_loc,
+            backup_loc: self.backup_loc.clone(),
+            backup_git: env::var(BACKUP_GIT_VAR).map_or(self.backup_git, |git| git == "true" || git == "1"),
+            audit_loc: self.audit_loc.clone(),
+            recordings_loc: self.recordings_loc.clone(),
+            auth: env::var(AUTH_VAR).map_or(self.auth, |auth| auth == "true" || auth == "1"),
+            tokens_loc: self.tokens_loc.clone(),
+            tls: env::var(TLS_VAR).map_or(self.tls, |tls| tls == "true" || tls == "1"),
+            tls_cert_loc: self.tls_cert_loc.clone(),
+            tls_key_loc: self.tls_key_loc.clone(),
+            tls_client_ca_loc: env::var(TLS_CLIENT_CA_VAR).ok().or(self.tls_client_ca_loc.clone()),
+            terminal: env::var(TERMINAL_VAR).map_or(self.terminal, |terminal| terminal == "true" || terminal == "1"),
+            terminal_bind_addresses,
+            terminal_base_port,
+            terminal_mode,
             mac_address,
             version: "1.0.0".to_string(),
         };
-        return config
+        Ok(config)
 
         // let path = Path::new("./src/settings.conf");
         // let mut file = match File::open(path) {
@@ -65,13 +117,86 @@ impl ConfigHandler {
     }
 }
 
+/// Comma separated addresses in `var`, `None` when it isn't set.
+fn env_addresses(var: &str) -> Option<Vec<String>> {
+    env::var(var).ok().map(|addresses| addresses.split(',')
+        .map(|addr| addr.trim().to_string())
+        .filter(|addr| !addr.is_empty())
+        .collect())
+}
+
+/// Port set in `var`, `None` when it isn't set.
+fn env_port(var: &str) -> io::Result<Option<u16>> {
+    match env::var(var) {
+        Ok(port) => port.trim().parse()
+            .map(Some)
+            .map_err(|why| invalid_var(var, format!("is not a valid port: {}", why))),
+        Err(_) => Ok(None),
+    }
+}
+
+fn invalid_var(var: &str, reason: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{} {}", var, reason))
+}
+
+/// Reads the agent UUID stored at `path`, creating it on first boot so the controller sees the
+/// same agent across restarts. A fresh UUID is derived from the machine-id when there is one,
+/// random otherwise.
+fn load_or_create_uuid(path: &str) -> String {
+    match fs::read_to_string(path) {
+        Ok(stored) => match Uuid::try_parse(stored.trim()) {
+            Ok(uuid) => return uuid.to_string(),
+            Err(why) => warn!("Stored uuid in {} is not valid ({}), generating a new one", path, why)
+        },
+        Err(why) => info!("Couldn't read uuid from {}: {}, generating a new one", path, why)
+    }
+
+    let uuid = fs::read_to_string(MACHINE_ID_LOC).ok()
+        .and_then(|machine_id| Uuid::try_parse(machine_id.trim()).ok())
+        .map(app_specific_uuid)
+        .unwrap_or_else(Uuid::new_v4);
+
+    if let Err(why) = fs::write(path, uuid.to_string()) {
+        error!("Couldn't persist uuid to {}: {}", path, why);
+    }
+    uuid.to_string()
+}
+
+/// HMAC-SHA256 of the app id keyed by the machine-id, shaped into a v4 UUID.
+pub fn app_specific_uuid(machine_id: Uuid) -> Uuid {
+    let mut mac = Hmac::<Sha256>::new_from_slice(machine_id.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(AGENT_ID_APP);
+    let digest = mac.finalize().into_bytes();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    Builder::from_random_bytes(bytes).into_uuid()
+}
+
 impl Default for ConfigHandler{
     fn default() -> Self {
         ConfigHandler{
             uuid: Uuid::new_v4().to_string(),
             ip_address: "127.0.0.1".to_string(),
+            bind_addresses: vec!["127.0.0.1".to_string()],
+            port: 8080,
             platform: "Windows".to_string(),
             templates_loc: "./templates".to_string(),
+            config_templates_loc: "./config_templates".to_string(),
+            uuid_loc: "./agent_uuid".to_string(),
+            backup_loc: "./backups".to_string(),
+            backup_git: false,
+            audit_loc: "./audit".to_string(),
+            recordings_loc: "./recordings".to_string(),
+            auth: true,
+            tokens_loc: "./api_tokens.json".to_string(),
+            tls: false,
+            tls_cert_loc: "./tls/cert.pem".to_string(),
+            tls_key_loc: "./tls/key.pem".to_string(),
+            tls_client_ca_loc: None,
+            terminal: false,
+            terminal_bind_addresses: vec!["127.0.0.1".to_string()],
+            terminal_base_port: 2000,
+            terminal_mode: TerminalMode::Telnet,
             mac_address: "00:00:00:00:00".to_string(),
             version: "1.0.0".to_string(),
         }
@@ -80,10 +205,15 @@ impl Default for ConfigHandler{
 
 impl Display for ConfigHandler{
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write(f, format_args!("Config: ip_address: {}, mac_address: {}, uuid: {}",
+        write(f, format_args!("Config: ip_address: {}, bind_addresses: {:?}, port: {}, auth: {}, tls: {}, terminal: {}, terminal_bind_addresses: {:?}, mac_address: {}, uuid: {}",
                               self.ip_address,
+                              self.bind_addresses,
+                              self.port,
+                              self.auth,
+                              self.tls,
+                              self.terminal,
+                              self.terminal_bind_addresses,
                               self.mac_address,
                               self.uuid))
     }
 }
-
@@ -1,11 +1,42 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Serialize, Deserialize)]
+use crate::handlers::terminal_handler::model::TerminalMode;
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
 pub struct ConfigHandler {
     pub uuid: String,
     pub ip_address: String,
+    pub bind_addresses: Vec<String>,
+    pub port: u16,
     pub platform: String,
     pub templates_loc: String,
+    pub config_templates_loc: String,
+    pub uuid_loc: String,
+    pub backup_loc: String,
+    pub backup_git: bool,
+    pub audit_loc: String,
+    /// Console sessions in asciicast format, see `RecordingHandler`.
+    pub recordings_loc: String,
+    /// Require API tokens, turning it off opens the whole API to anyone who can reach the agent.
+    pub auth: bool,
+    /// JSON list of the API tokens, see `ApiToken`.
+    pub tokens_loc: String,
+    /// Serve HTTPS instead of plain HTTP.
+    pub tls: bool,
+    /// Certificate and key in PEM, a self-signed pair is generated here when missing.
+    pub tls_cert_loc: String,
+    pub tls_key_loc: String,
+    /// CA of the controller's client certificate, enables mTLS when set.
+    pub tls_client_ca_loc: Option<String>,
+    /// Serve the device consoles on TCP ports, see `TerminalHandler`.
+    pub terminal: bool,
+    /// Where the terminal ports listen. Their login sends the token in cleartext, so only
+    /// loopback by default, reach them through an SSH tunnel or list other addresses here.
+    pub terminal_bind_addresses: Vec<String>,
+    /// Device `id` listens on this port plus `id`, 0 lets the OS pick.
+    pub terminal_base_port: u16,
+    pub terminal_mode: TerminalMode,
     pub mac_address: String,
     pub version: String,
-}
\ No newline at end of file
+}
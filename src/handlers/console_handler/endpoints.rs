@@ -0,0 +1,96 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::mpsc;
+use actix_web::{rt, web, Error, HttpRequest, HttpResponse};
+use actix_web::http::header::{HeaderValue, SEC_WEBSOCKET_PROTOCOL};
+use actix_web::web::{Data, Payload, Query};
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use crate::errors::execution_error::ExecutionError;
+use crate::handlers::audit_handler::model::Origin;
+use crate::handlers::auth_handler::function::{offers_bearer_protocol, BEARER_PROTOCOL};
+use crate::handlers::auth_handler::model::Admin;
+use crate::handlers::network_devices_handler::model::{DeviceLockQuery, NetworkDevicesHandler};
+
+/// Upgrades to a WebSocket bridged to the device's console. Text and binary frames are typed
+/// as they are, the output comes back as binary frames, which is what xterm.js' attach addon
+/// expects. The session takes the device for itself, API operations queue until it's closed.
+#[utoipa::path(
+    get,
+    path = "/devices/{id}/console",
+    tag = "devices",
+    params(("id" = u32, Path, description = "Device id"), DeviceLockQuery),
+    responses(
+        (status = 101, description = "WebSocket to the console, browsers pass the token as the `bearer` subprotocol followed by the token"),
+        (status = 403),
+        (status = 423),
+    ),
+)]
+async fn console(_admin: Admin, origin: Origin, path: web::Path<u32>, lock: Query<DeviceLockQuery>, req: HttpRequest, body: Payload, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<HttpResponse, Error> {
+    let id = path.into_inner();
+    network_devices_handler.get_device(id)?;
+    if !lock.wait.unwrap_or(true) {
+        network_devices_handler.check_available(id)?;
+    }
+    let (mut response, session, messages) = actix_ws::handle(&req, body)?;
+    if offers_bearer_protocol(&req) {
+        response.headers_mut().insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static(BEARER_PROTOCOL));
+    }
+
+    let (input_tx, input_rx) = mpsc::channel();
+    let (output_tx, output_rx) = unbounded_channel();
+    rt::spawn(async move {
+        let console = network_devices_handler.console_session(id, input_rx, output_tx, origin);
+        relay(session, messages, input_tx, output_rx, console).await;
+    });
+    Ok(response)
+}
+
+/// Passes frames between the WebSocket and the console session until one of them ends.
+/// Dropping `input` is what ends the session on the device's side.
+async fn relay(mut session: Session, mut messages: MessageStream, input: mpsc::Sender<Vec<u8>>, mut output: UnboundedReceiver<Vec<u8>>, console: impl Future<Output = Result<(), ExecutionError>>) {
+    let mut console = pin!(console);
+    let mut input = Some(input);
+    let result = loop {
+        tokio::select! {
+            result = &mut console => break result,
+            Some(printed) = output.recv() => {
+                if session.binary(printed).await.is_err() {
+                    input = None;
+                }
+            }
+            message = messages.recv(), if input.is_some() => match message {
+                Some(Ok(Message::Text(text))) => send(&mut input, text.as_bytes().to_vec()),
+                Some(Ok(Message::Binary(data))) => send(&mut input, data.to_vec()),
+                Some(Ok(Message::Ping(data))) => {
+                    let _ = session.pong(&data).await;
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => input = None,
+                Some(Ok(_)) => {}
+            },
+        }
+    };
+
+    while let Ok(printed) = output.try_recv() {
+        if session.binary(printed).await.is_err() {
+            return;
+        }
+    }
+    let reason = match result {
+        Ok(()) => CloseReason::from(CloseCode::Normal),
+        Err(why) => CloseReason { code: CloseCode::Error, description: Some(why.message) },
+    };
+    let _ = session.close(Some(reason)).await;
+}
+
+fn send(input: &mut Option<mpsc::Sender<Vec<u8>>>, data: Vec<u8>) {
+    if input.as_ref().is_some_and(|input| input.send(data).is_err()) {
+        *input = None;
+    }
+}
+
+/// Routes below `/api/v1`.
+pub fn init_co_endpoints(cfg: &mut web::ServiceConfig) {
+    cfg.route("/devices/{id}/console", web::get().to(console));
+}
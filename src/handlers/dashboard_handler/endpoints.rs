@@ -0,0 +1,210 @@
+use actix_web::{get, post, web, Error, HttpMessage, HttpRequest, HttpResponse};
+use actix_web::cookie::{time, Cookie, SameSite};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{ContentType, LOCATION};
+use actix_web::web::{Data, Form};
+use handlebars::Handlebars;
+use log::warn;
+use serde_json::{json, Value};
+
+use crate::errors::csrf_error::CsrfError;
+use crate::errors::execution_error::ExecutionError;
+use crate::handlers::audit_handler::model::Origin;
+use crate::handlers::auth_handler::function::{LOGIN_PATH, SESSION_COOKIE, SESSION_TTL};
+use crate::handlers::auth_handler::model::{AuthHandler, Identity, Operator};
+use crate::handlers::config_handler::model::ConfigHandler;
+use crate::handlers::dashboard_handler::model::{CsrfForm, HostnameForm, InterfaceForm, LoginForm, VlanForm};
+use crate::handlers::network_devices_handler::model::NetworkDevicesHandler;
+use crate::objects::interface::model::InterfaceDTO;
+use crate::objects::vlan::model::VlanDTO;
+
+/// Where the session cookie is sent, the API itself only takes bearer tokens.
+const COOKIE_PATH: &str = "/ui";
+
+#[get("/ui/login")]
+async fn login_page(req: HttpRequest, handlebars: Data<Handlebars<'_>>, auth_handler: Data<AuthHandler>) -> Result<HttpResponse, ExecutionError> {
+    if !auth_handler.is_enabled() {
+        return Ok(see_other("/ui"));
+    }
+    render(&req, &handlebars, "login", &json!({}))
+}
+
+#[post("/ui/login")]
+async fn login(req: HttpRequest, form: Form<LoginForm>, handlebars: Data<Handlebars<'_>>, auth_handler: Data<AuthHandler>, config: Data<ConfigHandler>) -> Result<HttpResponse, ExecutionError> {
+    let Some(session_id) = auth_handler.login(&form.token) else {
+        warn!("Failed dashboard login from {}", req.connection_info().realip_remote_addr().unwrap_or("unknown"));
+        let mut response = render(&req, &handlebars, "login", &json!({ "error": "Invalid token." }))?;
+        *response.status_mut() = StatusCode::UNAUTHORIZED;
+        return Ok(response);
+    };
+    let cookie = Cookie::build(SESSION_COOKIE, session_id)
+        .path(COOKIE_PATH)
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .secure(config.tls)
+        .max_age(time::Duration::seconds(SESSION_TTL.as_secs() as i64))
+        .finish();
+    let mut response = see_other("/ui");
+    response.add_cookie(&cookie).map_err(|why| ExecutionError { message: why.to_string() })?;
+    Ok(response)
+}
+
+#[post("/ui/logout")]
+async fn logout(req: HttpRequest, form: Form<CsrfForm>, auth_handler: Data<AuthHandler>) -> Result<HttpResponse, Error> {
+    auth_handler.check_csrf(&req, &form.csrf)?;
+    if let Some(cookie) = req.cookie(SESSION_COOKIE) {
+        auth_handler.logout(cookie.value());
+    }
+    let mut removal = Cookie::build(SESSION_COOKIE, "").path(COOKIE_PATH).finish();
+    removal.make_removal();
+    let mut response = see_other(LOGIN_PATH);
+    response.add_cookie(&removal).map_err(|why| ExecutionError { message: why.to_string() })?;
+    Ok(response)
+}
+
+#[get("/ui")]
+async fn devices_page(req: HttpRequest, handlebars: Data<Handlebars<'_>>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<HttpResponse, ExecutionError> {
+    let mut devices: Vec<Value> = network_devices_handler.get_devices().into_iter()
+        .map(|(id, device)| {
+            let mut device = json!(device);
+            device["id"] = json!(id);
+            device
+        })
+        .collect();
+    devices.sort_by_key(|device| device["id"].as_u64());
+
+    render(&req, &handlebars, "devices", &json!({ "devices": devices }))
+}
+
+#[get("/ui/device/{id}")]
+async fn device_page(req: HttpRequest, path: web::Path<u32>, handlebars: Data<Handlebars<'_>>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<HttpResponse, ExecutionError> {
+    device_page_with(&req, path.into_inner(), Ok(None), &handlebars, &network_devices_handler)
+}
+
+#[post("/ui/device/{id}/hostname")]
+async fn change_hostname(req: HttpRequest, _operator: Operator, origin: Origin, path: web::Path<u32>, form: Form<HostnameForm>, handlebars: Data<Handlebars<'_>>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<HttpResponse, Error> {
+    check_csrf(&req, &form.csrf)?;
+    let id = path.into_inner();
+    let outcome = network_devices_handler.change_hostname(id, form.into_inner().hostname, origin).await
+        .map(|device| Some(format!("Hostname changed to {}.", device.hostname)));
+    Ok(device_page_with(&req, id, outcome, &handlebars, &network_devices_handler)?)
+}
+
+#[post("/ui/device/{id}/vlan")]
+async fn add_vlan(req: HttpRequest, _operator: Operator, origin: Origin, path: web::Path<u32>, form: Form<VlanForm>, handlebars: Data<Handlebars<'_>>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<HttpResponse, Error> {
+    check_csrf(&req, &form.csrf)?;
+    let id = path.into_inner();
+    let form = form.into_inner();
+    let vlan = VlanDTO {
+        number: form.number,
+        name: form.name,
+        interfaces: Vec::new(),
+    };
+    let outcome = network_devices_handler.add_vlan(id, vlan, origin).await.map(Some);
+    Ok(device_page_with(&req, id, outcome, &handlebars, &network_devices_handler)?)
+}
+
+#[post("/ui/device/{id}/vlan/{vlan_id}/delete")]
+async fn delete_vlan(req: HttpRequest, _operator: Operator, origin: Origin, path: web::Path<(u32, u32)>, form: Form<CsrfForm>, handlebars: Data<Handlebars<'_>>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<HttpResponse, Error> {
+    check_csrf(&req, &form.csrf)?;
+    let (id, vlan_id) = path.into_inner();
+    let outcome = network_devices_handler.remove_vlan(id, vlan_id, origin).await.map(Some);
+    Ok(device_page_with(&req, id, outcome, &handlebars, &network_devices_handler)?)
+}
+
+#[post("/ui/device/{id}/interface/{interface_id}")]
+async fn configure_interface(req: HttpRequest, _operator: Operator, origin: Origin, path: web::Path<(u32, u32)>, form: Form<InterfaceForm>, handlebars: Data<Handlebars<'_>>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<HttpResponse, Error> {
+    check_csrf(&req, &form.csrf)?;
+    let (id, interface_id) = path.into_inner();
+    let form = form.into_inner();
+    let interface = InterfaceDTO {
+        ip_address: form.ip_address,
+        mask: form.mask,
+        status: form.status,
+    };
+    let outcome = network_devices_handler.configure_interface(id, interface_id, interface, origin).await
+        .map(|_| Some("Interface configured.".to_string()));
+    Ok(device_page_with(&req, id, outcome, &handlebars, &network_devices_handler)?)
+}
+
+#[post("/ui/device/{id}/reload_configs")]
+async fn reload_configs(req: HttpRequest, origin: Origin, path: web::Path<u32>, form: Form<CsrfForm>, handlebars: Data<Handlebars<'_>>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<HttpResponse, Error> {
+    check_csrf(&req, &form.csrf)?;
+    let id = path.into_inner();
+    let outcome = network_devices_handler.reload_configs(id, origin).await
+        .map(|_| Some("Configs reloaded.".to_string()));
+    Ok(device_page_with(&req, id, outcome, &handlebars, &network_devices_handler)?)
+}
+
+/// Renders the device page, showing the outcome of the form that was submitted, if any.
+fn device_page_with(req: &HttpRequest, id: u32, outcome: Result<Option<String>, ExecutionError>, handlebars: &Handlebars<'_>, network_devices_handler: &NetworkDevicesHandler) -> Result<HttpResponse, ExecutionError> {
+    let device = network_devices_handler.get_device(id)?;
+
+    let mut interfaces: Vec<Value> = device.interfaces.iter()
+        .map(|(interface_id, interface)| {
+            let mut interface = json!(interface);
+            interface["id"] = json!(interface_id);
+            interface
+        })
+        .collect();
+    interfaces.sort_by_key(|interface| interface["id"].as_u64());
+    let mut vlans: Vec<Value> = device.vlans.iter()
+        .map(|(number, vlan)| {
+            let mut vlan = json!(vlan);
+            vlan["number"] = json!(number);
+            vlan
+        })
+        .collect();
+    vlans.sort_by_key(|vlan| vlan["number"].as_u64());
+
+    let (message, error) = match outcome {
+        Ok(message) => (message, None),
+        Err(why) => (None, Some(why.message)),
+    };
+    render(req, handlebars, "device", &json!({
+        "id": id,
+        "device": device,
+        "interfaces": interfaces,
+        "vlans": vlans,
+        "message": message,
+        "error": error,
+    }))
+}
+
+/// Renders a page, with the name of the logged in user for the header and the CSRF token for its forms.
+fn render(req: &HttpRequest, handlebars: &Handlebars<'_>, template: &str, data: &Value) -> Result<HttpResponse, ExecutionError> {
+    let mut data = data.clone();
+    if let Some(identity) = req.extensions().get::<Identity>() {
+        data["user"] = json!(identity.name);
+    }
+    if let Some(csrf) = req.app_data::<Data<AuthHandler>>().and_then(|auth_handler| auth_handler.csrf_token(req)) {
+        data["csrf"] = json!(csrf);
+    }
+    let body = handlebars.render(template, &data)
+        .map_err(|why| ExecutionError { message: format!("Couldn't render {}: {}", template, why) })?;
+    Ok(HttpResponse::Ok().insert_header(ContentType::html()).body(body))
+}
+
+/// Refuses forms posted from other sites, see `AuthHandler::check_csrf`.
+fn check_csrf(req: &HttpRequest, csrf: &str) -> Result<(), CsrfError> {
+    req.app_data::<Data<AuthHandler>>()
+        .ok_or(CsrfError)
+        .and_then(|auth_handler| auth_handler.check_csrf(req, csrf))
+}
+
+fn see_other(location: &str) -> HttpResponse {
+    HttpResponse::SeeOther().insert_header((LOCATION, location)).finish()
+}
+
+pub fn init_dh_endpoints(cfg: &mut web::ServiceConfig) {
+    cfg.service(login_page);
+    cfg.service(login);
+    cfg.service(logout);
+    cfg.service(devices_page);
+    cfg.service(device_page);
+    cfg.service(change_hostname);
+    cfg.service(add_vlan);
+    cfg.service(delete_vlan);
+    cfg.service(configure_interface);
+    cfg.service(reload_configs);
+}
@@ -0,0 +1,33 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct LoginForm {
+    pub token: String,
+}
+
+/// Dashboard forms carry the CSRF token of the page they were sent from, see `AuthHandler::check_csrf`.
+#[derive(Debug, Deserialize)]
+pub struct CsrfForm {
+    pub csrf: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HostnameForm {
+    pub csrf: String,
+    pub hostname: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VlanForm {
+    pub csrf: String,
+    pub number: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InterfaceForm {
+    pub csrf: String,
+    pub ip_address: String,
+    pub mask: String,
+    pub status: String,
+}
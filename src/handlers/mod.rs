@@ -1,2 +1,27 @@
+use actix_web::dev::HttpServiceFactory;
+use actix_web::http::Method;
+use actix_web::middleware::DefaultHeaders;
+use actix_web::{guard, web, FromRequest, Handler, Responder};
+
 pub mod network_devices_handler;
-pub mod config_handler;
\ No newline at end of file
+pub mod config_handler;
+pub mod backup_handler;
+pub mod template_handler;
+pub mod dashboard_handler;
+pub mod audit_handler;
+pub mod auth_handler;
+pub mod console_handler;
+pub mod terminal_handler;
+pub mod recording_handler;
+
+/// Unversioned route from before `/api/v1`, answering with a `Deprecation` header so clients
+/// notice they should move.
+pub(crate) fn deprecated<F, Args>(path: &str, method: Method, handler: F) -> impl HttpServiceFactory
+    where F: Handler<Args>,
+          Args: FromRequest + 'static,
+          F::Output: Responder + 'static {
+    web::resource(path)
+        .guard(guard::Method(method.clone()))
+        .wrap(DefaultHeaders::new().add(("Deprecation", "true")))
+        .route(web::method(method).to(handler))
+}
@@ -1,75 +1,397 @@
-use std::collections::HashMap;
-use std::sync::Mutex;
-use actix_web::{delete, get, post, Responder, web};
-use actix_web::error::JsonPayloadError;
-use actix_web::web::{Data, Json};
+use std::collections::{BTreeMap, HashMap};
+use actix_web::{web, Either, Error, HttpResponse};
+use actix_web::http::Method;
+use actix_web::web::{Data, Json, Query};
 
+use crate::handlers::deprecated;
 use crate::errors::execution_error::ExecutionError;
-use crate::handlers::network_devices_handler::model::NetworkDevicesHandler;
-use crate::objects::device::model::NetworkDevice;
-use crate::objects::interface::model::InterfaceDTO;
-use crate::objects::vlan::model::VlanDTO;
+use crate::handlers::audit_handler::model::Origin;
+use crate::handlers::auth_handler::model::{Admin, Operator};
+use crate::handlers::network_devices_handler::model::{DeviceLockQuery, DryRunDTO, DryRunQuery, NetworkDevicesHandler};
+use crate::objects::config::function::section_diff;
+use crate::objects::config::model::{ConfigDiffDTO, ConfigQuery, ParsedConfig, RestoreDTO, RestoreReport, RunningConfigDTO};
+use crate::objects::device::model::{ChangesDTO, ChangesReport, DevicePatchDTO, NetworkDevice};
+use crate::objects::interface::model::{Interface, InterfaceDTO};
+use crate::objects::vlan::model::{Vlan, VlanDTO};
 
-#[get("/devices")]
-async fn get_network_devices(devices_handler: Data<Mutex<NetworkDevicesHandler>>) -> impl Responder {
-    let devices_data = &devices_handler.lock().unwrap().devices;
-    Ok::<Json<HashMap<u32,NetworkDevice>>,JsonPayloadError>(Json(devices_data.clone()))
+#[utoipa::path(
+    get,
+    path = "/devices",
+    tag = "devices",
+    responses(
+        (status = 200, description = "Devices by id", body = HashMap<u32, NetworkDevice>),
+    ),
+)]
+async fn get_network_devices(devices_handler: Data<NetworkDevicesHandler>) -> Json<HashMap<u32, NetworkDevice>> {
+    Json(devices_handler.get_devices())
 }
 
-#[get("/device/{id}")]
-async fn get_network_device(path: web::Path<u32>, network_devices_handler: Data<Mutex<NetworkDevicesHandler>>) -> Result<Json<NetworkDevice>, ExecutionError>{
+#[utoipa::path(
+    get,
+    path = "/devices/{id}",
+    tag = "devices",
+    params(("id" = u32, Path, description = "Device id")),
+    responses(
+        (status = 200, body = NetworkDevice),
+        (status = 400, description = "Unknown device"),
+    ),
+)]
+async fn get_network_device(path: web::Path<u32>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Json<NetworkDevice>, ExecutionError>{
     let id = path.into_inner();
-    let mut devices_handler = &mut network_devices_handler.lock().unwrap();
-    let device = devices_handler.get_device(id)?;
-    Ok(Json(device.clone()))
+    let device = network_devices_handler.get_device(id)?;
+    Ok(Json(device))
 }
 
-#[post("/device/{device_id}/vlan")]
-async fn add_vlan(path: web::Path<u32>, vlan_dto: Json<VlanDTO>, network_devices_handler: Data<Mutex<NetworkDevicesHandler>>) -> Result<String, ExecutionError> {
+#[utoipa::path(
+    post,
+    path = "/devices/{id}/vlans",
+    tag = "devices",
+    params(("id" = u32, Path, description = "Device id"), DeviceLockQuery, DryRunQuery),
+    request_body = VlanDTO,
+    responses(
+        (status = 200, description = "Result message, or the CLI lines on a dry run", body = String),
+        (status = 403),
+        (status = 423),
+    ),
+)]
+async fn add_vlan(_operator: Operator, origin: Origin, path: web::Path<u32>, lock: Query<DeviceLockQuery>, dry_run: Query<DryRunQuery>, vlan_dto: Json<VlanDTO>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Either<Json<DryRunDTO>, String>, Error> {
     let id = path.into_inner();
-    let mut devices_handler = network_devices_handler.lock().unwrap();
-    devices_handler.add_vlan(id, vlan_dto.into_inner())
+    if is_dry_run(&dry_run) {
+        let commands = network_devices_handler.get_device(id)?.add_vlan_commands(&vlan_dto)?;
+        return Ok(Either::Left(Json(DryRunDTO { commands })));
+    }
+    check_lock(&network_devices_handler, id, &lock)?;
+    Ok(Either::Right(network_devices_handler.add_vlan(id, vlan_dto.into_inner(), origin).await?))
 }
 
-#[delete("/device/{device_id}/vlan/{vlan_id}")]
-async fn delete_vlan(path: web::Path<(u32, u32)> ,network_devices_handler: Data<Mutex<NetworkDevicesHandler>>) -> Result<String, ExecutionError> {
+#[utoipa::path(
+    delete,
+    path = "/devices/{id}/vlans/{vlan_id}",
+    tag = "devices",
+    params(("id" = u32, Path, description = "Device id"), ("vlan_id" = u32, Path), DeviceLockQuery, DryRunQuery),
+    responses(
+        (status = 200, description = "Result message, or the CLI lines on a dry run", body = String),
+        (status = 403),
+        (status = 423),
+    ),
+)]
+async fn delete_vlan(_operator: Operator, origin: Origin, path: web::Path<(u32, u32)>, lock: Query<DeviceLockQuery>, dry_run: Query<DryRunQuery>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Either<Json<DryRunDTO>, String>, Error> {
     let (device_id, vlan_id) = path.into_inner();
-    let mut devices_handler = &mut network_devices_handler.lock().unwrap();
-    devices_handler.remove_vlan(device_id, vlan_id)
+    if is_dry_run(&dry_run) {
+        let commands = network_devices_handler.get_device(device_id)?.remove_vlan_commands(vlan_id);
+        return Ok(Either::Left(Json(DryRunDTO { commands })));
+    }
+    check_lock(&network_devices_handler, device_id, &lock)?;
+    Ok(Either::Right(network_devices_handler.remove_vlan(device_id, vlan_id, origin).await?))
 }
 
-#[post("/device/{id}/hostname/{hostname}")]
-async fn change_hostname(path: web::Path<(u32, String)>, network_devices_handler: Data<Mutex<NetworkDevicesHandler>>) -> Result<Json<NetworkDevice>, ExecutionError>{
+#[utoipa::path(
+    get,
+    path = "/devices/{id}/vlans",
+    tag = "devices",
+    params(("id" = u32, Path, description = "Device id")),
+    responses((status = 200, description = "Vlans by id", body = HashMap<u32, Vlan>)),
+)]
+async fn list_vlans(path: web::Path<u32>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Json<HashMap<u32, Vlan>>, ExecutionError> {
+    let id = path.into_inner();
+    Ok(Json(network_devices_handler.get_device(id)?.vlans))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/devices/{id}",
+    tag = "devices",
+    params(("id" = u32, Path, description = "Device id"), DeviceLockQuery, DryRunQuery),
+    request_body = DevicePatchDTO,
+    responses(
+        (status = 200, body = NetworkDevice),
+        (status = 200, description = "Dry run", body = DryRunDTO),
+        (status = 403),
+        (status = 423),
+    ),
+)]
+async fn update_device(_operator: Operator, origin: Origin, path: web::Path<u32>, lock: Query<DeviceLockQuery>, dry_run: Query<DryRunQuery>, patch: Json<DevicePatchDTO>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Either<Json<DryRunDTO>, Json<NetworkDevice>>, Error> {
+    let id = path.into_inner();
+    match patch.into_inner().hostname {
+        Some(hostname) => change_hostname(&network_devices_handler, id, hostname, &lock, &dry_run, origin).await,
+        None if is_dry_run(&dry_run) => Ok(Either::Left(Json(DryRunDTO { commands: Vec::new() }))),
+        None => Ok(Either::Right(Json(network_devices_handler.get_device(id)?))),
+    }
+}
+
+/// Deprecated `POST /device/{id}/hostname/{hostname}`, see `update_device`.
+async fn legacy_change_hostname(_operator: Operator, origin: Origin, path: web::Path<(u32, String)>, lock: Query<DeviceLockQuery>, dry_run: Query<DryRunQuery>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Either<Json<DryRunDTO>, Json<NetworkDevice>>, Error> {
     let (id, hostname) = path.into_inner();
-    let mut devices_handler = network_devices_handler.lock().unwrap();
-    let device_conf = devices_handler.change_hostname(id, hostname.as_str())?;
-    Ok(Json(device_conf.clone()))
+    change_hostname(&network_devices_handler, id, hostname, &lock, &dry_run, origin).await
 }
 
-#[post("/device/{device_id}/interface/{interface_id}")]
-async fn conf_interface(path: web::Path<(u32, u32)>, interface_dto: Json<InterfaceDTO>, network_devices_handler: Data<Mutex<NetworkDevicesHandler>>) -> Result<Json<NetworkDevice>, ExecutionError> {
+async fn change_hostname(network_devices_handler: &NetworkDevicesHandler, id: u32, hostname: String, lock: &DeviceLockQuery, dry_run: &DryRunQuery, origin: Origin) -> Result<Either<Json<DryRunDTO>, Json<NetworkDevice>>, Error> {
+    if is_dry_run(dry_run) {
+        let commands = network_devices_handler.get_device(id)?.hostname_commands(&hostname)?;
+        return Ok(Either::Left(Json(DryRunDTO { commands })));
+    }
+    check_lock(network_devices_handler, id, lock)?;
+    let device_conf = network_devices_handler.change_hostname(id, hostname, origin).await?;
+    Ok(Either::Right(Json(device_conf)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/devices/{id}/interfaces",
+    tag = "devices",
+    params(("id" = u32, Path, description = "Device id")),
+    responses((status = 200, description = "Interfaces by name", body = BTreeMap<String, Interface>)),
+)]
+async fn list_interfaces(path: web::Path<u32>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Json<BTreeMap<String, Interface>>, ExecutionError> {
+    let id = path.into_inner();
+    let interfaces = network_devices_handler.get_device(id)?.interfaces.into_values()
+        .map(|interface| (interface.name(), interface))
+        .collect();
+    Ok(Json(interfaces))
+}
+
+#[utoipa::path(
+    put,
+    path = "/devices/{id}/interfaces/{name}",
+    tag = "devices",
+    params(
+        ("id" = u32, Path, description = "Device id"),
+        ("name" = String, Path, description = "Interface name like FastEthernet0/1, the slash may be sent as is"),
+        DeviceLockQuery,
+        DryRunQuery,
+    ),
+    request_body = InterfaceDTO,
+    responses(
+        (status = 200, body = NetworkDevice),
+        (status = 200, description = "Dry run", body = DryRunDTO),
+        (status = 403),
+        (status = 423),
+    ),
+)]
+async fn conf_interface(_operator: Operator, origin: Origin, path: web::Path<(u32, String)>, lock: Query<DeviceLockQuery>, dry_run: Query<DryRunQuery>, interface_dto: Json<InterfaceDTO>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Either<Json<DryRunDTO>, Json<NetworkDevice>>, Error> {
+    let (device_id, name) = path.into_inner();
+    let interface_id = network_devices_handler.get_device(device_id)?.interface_id(&name)?;
+    configure_interface(&network_devices_handler, device_id, interface_id, interface_dto.into_inner(), &lock, &dry_run, origin).await
+}
+
+/// Deprecated `POST /device/{device_id}/interface/{interface_id}`, see `conf_interface`.
+async fn legacy_conf_interface(_operator: Operator, origin: Origin, path: web::Path<(u32, u32)>, lock: Query<DeviceLockQuery>, dry_run: Query<DryRunQuery>, interface_dto: Json<InterfaceDTO>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Either<Json<DryRunDTO>, Json<NetworkDevice>>, Error> {
     let (device_id, interface_id) = path.into_inner();
-    let mut devices_handler = network_devices_handler.lock().unwrap();
-    let device = devices_handler.configure_interface(device_id, interface_id, interface_dto.into_inner())?;
+    configure_interface(&network_devices_handler, device_id, interface_id, interface_dto.into_inner(), &lock, &dry_run, origin).await
+}
+
+async fn configure_interface(network_devices_handler: &NetworkDevicesHandler, device_id: u32, interface_id: u32, interface_dto: InterfaceDTO, lock: &DeviceLockQuery, dry_run: &DryRunQuery, origin: Origin) -> Result<Either<Json<DryRunDTO>, Json<NetworkDevice>>, Error> {
+    if is_dry_run(dry_run) {
+        let commands = network_devices_handler.get_device(device_id)?.interface_commands(interface_id, &interface_dto)?;
+        return Ok(Either::Left(Json(DryRunDTO { commands })));
+    }
+    check_lock(network_devices_handler, device_id, lock)?;
+    let device = network_devices_handler.configure_interface(device_id, interface_id, interface_dto, origin).await?;
+
+    Ok(Either::Right(Json(device)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/devices/{id}/changes",
+    tag = "changes",
+    params(("id" = u32, Path, description = "Device id"), DeviceLockQuery, DryRunQuery),
+    request_body = ChangesDTO,
+    responses(
+        (status = 200, body = ChangesReport),
+        (status = 200, description = "Dry run", body = DryRunDTO),
+        (status = 403),
+        (status = 423),
+    ),
+)]
+async fn apply_changes(_operator: Operator, origin: Origin, path: web::Path<u32>, lock: Query<DeviceLockQuery>, dry_run: Query<DryRunQuery>, changes: Json<ChangesDTO>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Either<Json<DryRunDTO>, Json<ChangesReport>>, Error> {
+    let id = path.into_inner();
+    if is_dry_run(&dry_run) {
+        let commands = network_devices_handler.changes_commands(id, &changes.changes)?;
+        return Ok(Either::Left(Json(DryRunDTO { commands })));
+    }
+    check_lock(&network_devices_handler, id, &lock)?;
+    let changes = changes.into_inner();
+    let report = network_devices_handler.apply_changes(id, changes.changes, changes.confirm_minutes, origin).await?;
+
+    Ok(Either::Right(Json(report)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/devices/{id}/changes/confirm",
+    tag = "changes",
+    params(("id" = u32, Path, description = "Device id"), DeviceLockQuery, DryRunQuery),
+    responses(
+        (status = 200, body = NetworkDevice),
+        (status = 200, description = "Dry run", body = DryRunDTO),
+        (status = 400, description = "No change waiting for confirmation"),
+        (status = 403),
+    ),
+)]
+async fn confirm_changes(_operator: Operator, origin: Origin, path: web::Path<u32>, lock: Query<DeviceLockQuery>, dry_run: Query<DryRunQuery>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Either<Json<DryRunDTO>, Json<NetworkDevice>>, Error> {
+    let id = path.into_inner();
+    if is_dry_run(&dry_run) {
+        let commands = network_devices_handler.get_device(id)?.confirm_commands()?;
+        return Ok(Either::Left(Json(DryRunDTO { commands })));
+    }
+    check_lock(&network_devices_handler, id, &lock)?;
+    let device = network_devices_handler.confirm_changes(id, origin).await?;
 
-    Ok(Json(device.clone()))
+    Ok(Either::Right(Json(device)))
 }
 
-#[get("/device/{id}/reload_configs")]
-async fn reload_configs(path: web::Path<u32>, network_devices_handler: Data<Mutex<NetworkDevicesHandler>>) -> Result<Json<NetworkDevice>, ExecutionError> {
+#[utoipa::path(
+    post,
+    path = "/devices/{id}/config/reload",
+    tag = "config",
+    params(("id" = u32, Path, description = "Device id"), DeviceLockQuery),
+    responses((status = 200, body = NetworkDevice)),
+)]
+async fn reload_configs(origin: Origin, path: web::Path<u32>, lock: Query<DeviceLockQuery>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Json<NetworkDevice>, Error> {
     let id = path.into_inner();
-    let mut devices_manager = network_devices_handler.lock().unwrap();
-    let device = devices_manager.reload_configs(id)?;
+    check_lock(&network_devices_handler, id, &lock)?;
+    let device = network_devices_handler.reload_configs(id, origin).await?;
 
-    Ok(Json(device.clone()))
+    Ok(Json(device))
 }
 
+#[utoipa::path(
+    get,
+    path = "/devices/{id}/config/running",
+    tag = "config",
+    params(("id" = u32, Path, description = "Device id"), ConfigQuery),
+    responses(
+        (status = 200, body = RunningConfigDTO),
+        (status = 200, description = "With format=text", body = String, content_type = "text/plain"),
+    ),
+)]
+async fn running_config(origin: Origin, path: web::Path<u32>, query: Query<ConfigQuery>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<HttpResponse, Error> {
+    let id = path.into_inner();
+    let text = network_devices_handler.running_config(id, query.refresh.unwrap_or(false), origin).await?;
+
+    if query.format.as_deref() == Some("text") {
+        return Ok(HttpResponse::Ok().content_type("text/plain; charset=utf-8").body(text));
+    }
+    let config = ParsedConfig::parse(&text);
+    Ok(HttpResponse::Ok().json(RunningConfigDTO { text, config }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/devices/{id}/config/diff",
+    tag = "config",
+    params(("id" = u32, Path, description = "Device id"), ConfigQuery),
+    responses(
+        (status = 200, body = ConfigDiffDTO),
+        (status = 200, description = "With format=text", body = String, content_type = "text/x-diff"),
+    ),
+)]
+async fn config_diff(origin: Origin, path: web::Path<u32>, query: Query<ConfigQuery>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<HttpResponse, Error> {
+    let id = path.into_inner();
+    let device = network_devices_handler.configs(id, query.refresh.unwrap_or(false), origin).await?;
+    let diff = section_diff(&ParsedConfig::parse(&device.startup_config), "startup-config",
+                            &ParsedConfig::parse(&device.running_config), "running-config");
+
+    if query.format.as_deref() == Some("text") {
+        return Ok(HttpResponse::Ok().content_type("text/x-diff; charset=utf-8").body(diff));
+    }
+    Ok(HttpResponse::Ok().json(ConfigDiffDTO { unsaved_changes: device.unsaved_changes, diff }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/devices/{id}/config/save",
+    tag = "config",
+    params(("id" = u32, Path, description = "Device id"), DeviceLockQuery, DryRunQuery),
+    responses(
+        (status = 200, body = NetworkDevice),
+        (status = 200, description = "Dry run", body = DryRunDTO),
+        (status = 403),
+        (status = 423),
+    ),
+)]
+async fn save_config(_operator: Operator, origin: Origin, path: web::Path<u32>, lock: Query<DeviceLockQuery>, dry_run: Query<DryRunQuery>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Either<Json<DryRunDTO>, Json<NetworkDevice>>, Error> {
+    let id = path.into_inner();
+    if is_dry_run(&dry_run) {
+        let commands = network_devices_handler.get_device(id)?.save_commands();
+        return Ok(Either::Left(Json(DryRunDTO { commands })));
+    }
+    check_lock(&network_devices_handler, id, &lock)?;
+    let device = network_devices_handler.save_config(id, origin).await?;
+
+    Ok(Either::Right(Json(device)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/devices/{id}/config/restore",
+    tag = "config",
+    params(("id" = u32, Path, description = "Device id"), DeviceLockQuery, DryRunQuery),
+    request_body = RestoreDTO,
+    responses(
+        (status = 200, body = RestoreReport),
+        (status = 200, description = "Dry run", body = DryRunDTO),
+        (status = 403),
+        (status = 423),
+    ),
+)]
+async fn restore_config(_admin: Admin, origin: Origin, path: web::Path<u32>, lock: Query<DeviceLockQuery>, dry_run: Query<DryRunQuery>, restore: Json<RestoreDTO>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Either<Json<DryRunDTO>, Json<RestoreReport>>, Error> {
+    let id = path.into_inner();
+    if is_dry_run(&dry_run) {
+        let commands = network_devices_handler.restore_commands(id, restore.into_inner())?;
+        return Ok(Either::Left(Json(DryRunDTO { commands })));
+    }
+    check_lock(&network_devices_handler, id, &lock)?;
+    let report = network_devices_handler.restore_config(id, restore.into_inner(), origin).await?;
+
+    Ok(Either::Right(Json(report)))
+}
+
+pub(crate) fn check_lock(network_devices_handler: &NetworkDevicesHandler, id: u32, lock: &DeviceLockQuery) -> Result<(), Error> {
+    if !lock.wait.unwrap_or(true) {
+        network_devices_handler.check_available(id)?;
+    }
+    Ok(())
+}
+
+/// Dry runs only read the last known state of the device, so they don't wait for its lock.
+pub(crate) fn is_dry_run(dry_run: &DryRunQuery) -> bool {
+    dry_run.dry_run.unwrap_or(false)
+}
+
+/// Routes below `/api/v1`.
 pub fn init_nd_endpoints(cfg: &mut web::ServiceConfig) {
-    cfg.service(get_network_devices);
-    cfg.service(get_network_device);
-    cfg.service(change_hostname);
-    cfg.service(add_vlan);
-    cfg.service(delete_vlan);
-    cfg.service(conf_interface);
-    cfg.service(reload_configs);
-}
\ No newline at end of file
+    cfg.route("/devices", web::get().to(get_network_devices));
+    cfg.route("/devices/{id}", web::get().to(get_network_device));
+    cfg.route("/devices/{id}", web::patch().to(update_device));
+    cfg.route("/devices/{id}/vlans", web::get().to(list_vlans));
+    cfg.route("/devices/{id}/vlans", web::post().to(add_vlan));
+    cfg.route("/devices/{id}/vlans/{vlan_id}", web::delete().to(delete_vlan));
+    cfg.route("/devices/{id}/interfaces", web::get().to(list_interfaces));
+    cfg.route("/devices/{id}/interfaces/{name:.+}", web::put().to(conf_interface));
+    cfg.route("/devices/{id}/changes", web::post().to(apply_changes));
+    cfg.route("/devices/{id}/changes/confirm", web::post().to(confirm_changes));
+    cfg.route("/devices/{id}/config/reload", web::post().to(reload_configs));
+    cfg.route("/devices/{id}/config/running", web::get().to(running_config));
+    cfg.route("/devices/{id}/config/diff", web::get().to(config_diff));
+    cfg.route("/devices/{id}/config/save", web::post().to(save_config));
+    cfg.route("/devices/{id}/config/restore", web::post().to(restore_config));
+}
+
+/// Unversioned routes from before `/api/v1`, kept working for existing clients.
+pub fn init_nd_legacy_endpoints(cfg: &mut web::ServiceConfig) {
+    cfg.service(deprecated("/devices", Method::GET, get_network_devices));
+    cfg.service(deprecated("/device/{id}", Method::GET, get_network_device));
+    cfg.service(deprecated("/device/{id}/hostname/{hostname}", Method::POST, legacy_change_hostname));
+    cfg.service(deprecated("/device/{device_id}/vlan", Method::POST, add_vlan));
+    cfg.service(deprecated("/device/{device_id}/vlan/{vlan_id}", Method::DELETE, delete_vlan));
+    cfg.service(deprecated("/device/{device_id}/interface/{interface_id}", Method::POST, legacy_conf_interface));
+    cfg.service(deprecated("/device/{id}/changes", Method::POST, apply_changes));
+    cfg.service(deprecated("/device/{id}/changes/confirm", Method::POST, confirm_changes));
+    cfg.service(deprecated("/device/{id}/reload_configs", Method::GET, reload_configs));
+    cfg.service(deprecated("/device/{id}/config/running", Method::GET, running_config));
+    cfg.service(deprecated("/device/{id}/config/diff", Method::GET, config_diff));
+    cfg.service(deprecated("/device/{id}/config/save", Method::POST, save_config));
+    cfg.service(deprecated("/device/{id}/config/restore", Method::POST, restore_config));
+}
@@ -1,118 +1,337 @@
 use std::collections::HashMap;
-use std::io::Read;
+use std::sync::mpsc;
+use std::time::Duration;
 use serial2::SerialPort;
+use tokio::sync::mpsc::UnboundedSender;
+use crate::errors::device_busy_error::DeviceBusyError;
 use crate::errors::execution_error::ExecutionError;
 
 use super::model::NetworkDevicesHandler;
-use crate::objects::device::model::NetworkDevice;
+use super::worker::DeviceWorker;
+use crate::handlers::audit_handler::model::{AuditHandler, AuditSession, Origin};
+use crate::handlers::backup_handler::model::BackupHandler;
+use crate::handlers::recording_handler::model::RecordingHandler;
+use crate::objects::config::model::{RestoreDTO, RestoreReport};
+use crate::objects::console::model::ConsoleHandle;
+use crate::objects::device::model::{ChangesReport, DeviceChange, NetworkDevice};
 use crate::objects::interface::model::InterfaceDTO;
 use crate::objects::vlan::model::VlanDTO;
 
+/// How long an interactive console session may go without input.
+const CONSOLE_IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
 impl Default for NetworkDevicesHandler {
     fn default() -> Self {
-        let ports = SerialPort::available_ports().expect("Couldn't read Serial Port list");
-        let response = &mut "".to_string();
-        let mut network_devices:HashMap<u32, NetworkDevice> = HashMap::new();
-        let mut next_id = 1;
-        for p in ports {
-            println!("Port: {:?}", p);
-            match SerialPort::open(p.clone(), 9600) {
-                Ok(mut port) => {
-                    match port.write("show version\n".as_ref()) {
-                        Ok(_res) => {
-                            match port.read_to_string(response) {
-                                Ok(_) => {}
-                                Err(why) => println!("Couldn't read: {}", why)
-                            }
-                            println!("{}",response);
-                            if response.contains("Cisco") {
-                                response.clear();
-                                port.write("hostname\n".as_ref()).unwrap();
-                                match port.read_to_string(response) {
-                                    Ok(_) => {}
-                                    Err(_why) => {}
-                                }
-                                let r_s = response.to_string();
-                                let output = r_s.split_once("\n").unwrap();
-                                network_devices.insert(next_id, NetworkDevice {
-                                    serial_number: "".to_string(),
-                                    ip_address: "".to_string(),
-                                    s_port: p.to_str().unwrap().to_string(),
-                                    hostname: output.1.trim().to_string(),
-                                    vlans: HashMap::new(),
-                                    interfaces: Default::default(),
-                                    startup_config: "".to_string(),
-                                    running_config: "".to_string(),
-                                });
-                                next_id += 1;
-                            }
+        NetworkDevicesHandler::new(discover_devices())
+    }
+}
+
+/// Probes every serial port for a Cisco device and returns the ones that answered.
+fn discover_devices() -> HashMap<u32, NetworkDevice> {
+    let ports = SerialPort::available_ports().expect("Couldn't read Serial Port list");
+    let mut network_devices:HashMap<u32, NetworkDevice> = HashMap::new();
+    let mut next_id = 1;
+    for p in ports {
+        println!("Port: {:?}", p);
+        let s_port = p.to_string_lossy().to_string();
+        let console = ConsoleHandle::serial(&s_port);
+        match console.execute("show version\n") {
+            Ok(response) => {
+                println!("{}",response);
+                if response.contains("Cisco") {
+                    // the prompt printed after an empty line is the hostname followed by > or #
+                    let hostname = match console.execute("\n") {
+                        Ok(prompt) => prompt.lines()
+                            .map(|line| line.trim())
+                            .rfind(|line| !line.is_empty())
+                            .unwrap_or_default()
+                            .trim_end_matches(['>', '#'])
+                            .to_string(),
+                        Err(why) => {
+                            println!("Couldn't read hostname: {}", why);
+                            "".to_string()
                         }
-                        Err(why) => println!("Couldn't write: {}", why)
-                    }
+                    };
+                    network_devices.insert(next_id, NetworkDevice {
+                        serial_number: parse_serial_number(&response),
+                        s_port,
+                        ip_address: "".to_string(),
+                        hostname,
+                        console,
+                        ..Default::default()
+                    });
+                    next_id += 1;
                 }
-                Err(why) => println!("Couldn't open: {}", why)
             }
-        }
-
-        NetworkDevicesHandler{
-            devices: network_devices,
+            Err(why) => println!("Couldn't query {}: {}", s_port, why)
         }
     }
+    network_devices
+}
+
+/// Serial number from `show version`, switches print it as `System serial number`,
+/// routers only as `Processor board ID`.
+fn parse_serial_number(show_version: &str) -> String {
+    let find = |label: &str| show_version.lines()
+        .find_map(|line| line.trim().strip_prefix(label))
+        .map(|rest| rest.trim_start_matches([' ', ':']).split_whitespace().next().unwrap_or_default().to_string());
+    find("System serial number")
+        .or_else(|| find("Processor board ID"))
+        .unwrap_or_default()
 }
+
 impl NetworkDevicesHandler {
-    pub fn read_vlans(&mut self) {
-        for (_, device) in self.devices.iter_mut() {
-            device.read_vlans();
+    pub fn new(devices: HashMap<u32, NetworkDevice>) -> Self {
+        NetworkDevicesHandler {
+            devices: devices.into_iter()
+                .map(|(id, device)| (id, DeviceWorker::spawn(id, device)))
+                .collect(),
+            backup_handler: None,
+            audit_handler: None,
+            recording_handler: None,
+            console_idle_timeout: CONSOLE_IDLE_TIMEOUT,
         }
     }
 
-    pub fn read_interfaces(&mut self) {
-        for (_, device) in self.devices.iter_mut() {
-            device.read_interfaces().expect("TODO: panic message");
+    /// Archives every config read from the devices with `backup_handler`.
+    pub fn with_backups(mut self, backup_handler: BackupHandler) -> Self {
+        self.backup_handler = Some(backup_handler);
+        self
+    }
+
+    /// Records every command sent to the devices with `audit_handler`.
+    pub fn with_audit(mut self, audit_handler: AuditHandler) -> Self {
+        self.audit_handler = Some(audit_handler);
+        self
+    }
+
+    /// Records every console session on the devices with `recording_handler`.
+    pub fn with_recordings(mut self, recording_handler: RecordingHandler) -> Self {
+        self.recording_handler = Some(recording_handler);
+        self
+    }
+
+    /// Ends interactive console sessions after `timeout` without input.
+    pub fn with_console_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.console_idle_timeout = timeout;
+        self
+    }
+
+    pub fn audit(&self) -> Result<&AuditHandler, ExecutionError> {
+        self.audit_handler.as_ref().ok_or(ExecutionError { message: "Audit log is disabled.".to_string() })
+    }
+
+    pub fn recordings(&self) -> Result<&RecordingHandler, ExecutionError> {
+        self.recording_handler.as_ref().ok_or(ExecutionError { message: "Session recording is disabled.".to_string() })
+    }
+
+    pub fn backups(&self) -> Result<&BackupHandler, ExecutionError> {
+        self.backup_handler.as_ref().ok_or(ExecutionError { message: "Config archive is disabled.".to_string() })
+    }
+
+    /// Queues reading interfaces and vlans on every device without waiting for the results.
+    pub fn refresh(&self) {
+        for (id, worker) in &self.devices {
+            let origin = Origin::agent("refresh");
+            let session = self.audit_session(*id, origin.clone());
+            let recordings = self.recording_handler.clone();
+            worker.submit(move |device| audited(device, session, recordings.map(|recordings| (recordings, origin)), |device| {
+                if let Err(why) = device.read_interfaces() {
+                    println!("Couldn't read interfaces of {}: {}", device.hostname, why);
+                }
+                device.read_vlans();
+            }));
         }
     }
 
-    pub fn get_device(&mut self, id: u32) -> Result<&mut NetworkDevice, ExecutionError> {
-        match self.devices.get_mut(&id) {
-            Some(device) => Ok(device),
+    /// Runs `job` on the device's worker, recording the commands it sends for `origin`.
+    async fn run<F, R>(&self, device_id: u32, origin: Origin, job: F) -> Result<R, ExecutionError>
+        where F: FnOnce(&mut NetworkDevice) -> R + Send + 'static,
+              R: Send + 'static {
+        let session = self.audit_session(device_id, origin.clone());
+        let recordings = self.recording_handler.clone().map(|recordings| (recordings, origin));
+        self.get_worker(device_id)?.run(move |device| audited(device, session, recordings, job)).await
+    }
+
+    fn audit_session(&self, device_id: u32, origin: Origin) -> Option<AuditSession> {
+        self.audit_handler.clone().map(|log| AuditSession { log, device_id, origin })
+    }
+
+    fn get_worker(&self, id: u32) -> Result<&DeviceWorker, ExecutionError> {
+        match self.devices.get(&id) {
+            Some(worker) => Ok(worker),
             None => Err(ExecutionError{message: "Couldnt find device.".to_string()})
         }
     }
 
-    pub fn add_vlan(&mut self, id: u32, vlan: VlanDTO) -> Result<String, ExecutionError> {
-        let mut device = self.get_device(id)?;
-        device.add_vlan(vlan)
+    /// Fails when the device is already running an operation, for callers that would rather
+    /// not queue behind it. The check isn't atomic with queueing the next operation.
+    pub fn check_available(&self, id: u32) -> Result<(), DeviceBusyError> {
+        match self.devices.get(&id) {
+            Some(worker) if worker.is_busy() => Err(DeviceBusyError { device_id: id }),
+            _ => Ok(())
+        }
+    }
+
+    /// State of the device after its last finished operation, doesn't wait for running ones.
+    pub fn get_device(&self, id: u32) -> Result<NetworkDevice, ExecutionError> {
+        Ok(self.get_worker(id)?.snapshot())
     }
 
-    pub fn remove_vlan(&mut self, device_id: u32, vlan_id: u32) -> Result<String, ExecutionError> {
-        let mut device = self.get_device(device_id)?;
-        device.remove_vlan(vlan_id)
+    pub fn get_devices(&self) -> HashMap<u32, NetworkDevice> {
+        self.devices.iter()
+            .map(|(id, worker)| (*id, worker.snapshot()))
+            .collect()
     }
 
-    pub fn change_hostname(&mut self, device_id: u32, hostname: &str) -> Result<&NetworkDevice, ExecutionError> {
-        let mut device = self.get_device(device_id)?;
-        match device.execute_command(("en \nconf t \nhostname".to_owned() + &*hostname + "\n").as_str()) {
-            Ok(_response) => {
-                device.hostname = hostname.to_string();
-                Ok(device)
-            }
-            Err(_why) => { //This error should probably be piped to some kind of per device error handling for case when it stops working mid session
-                Err(ExecutionError { message: "Couldn't change hostname".to_string()})
-            }
+    pub async fn add_vlan(&self, id: u32, vlan: VlanDTO, origin: Origin) -> Result<String, ExecutionError> {
+        self.run(id, origin, move |device| device.add_vlan(vlan)).await?
+    }
+
+    pub async fn remove_vlan(&self, device_id: u32, vlan_id: u32, origin: Origin) -> Result<String, ExecutionError> {
+        self.run(device_id, origin, move |device| device.remove_vlan(vlan_id)).await?
+    }
+
+    pub async fn change_hostname(&self, device_id: u32, hostname: String, origin: Origin) -> Result<NetworkDevice, ExecutionError> {
+        self.run(device_id, origin, move |device| {
+            device.change_hostname(&hostname).cloned()
+        }).await?
+    }
+
+    pub async fn configure_interface(&self, device_id: u32, interface_id: u32, interface_dto: InterfaceDTO, origin: Origin) -> Result<NetworkDevice, ExecutionError> {
+        self.run(device_id, origin, move |device| {
+            device.configure_interface(interface_id, interface_dto).map(|device| device.clone())
+        }).await?
+    }
+
+    pub async fn reload_configs(&self, device_id: u32, origin: Origin) -> Result<NetworkDevice, ExecutionError> {
+        let backups = self.backup_handler.clone();
+        let trigger = origin.to_string();
+        self.run(device_id, origin, move |device| {
+            device.read_running_config()?;
+            device.read_startup_config()?;
+            archive(&backups, device, &trigger);
+            Ok(device.clone())
+        }).await?
+    }
+
+    pub async fn running_config(&self, device_id: u32, refresh: bool, origin: Origin) -> Result<String, ExecutionError> {
+        if !refresh {
+            return Ok(self.get_worker(device_id)?.snapshot().running_config);
+        }
+        let backups = self.backup_handler.clone();
+        let trigger = origin.to_string();
+        self.run(device_id, origin, move |device| {
+            device.read_running_config()?;
+            archive(&backups, device, &trigger);
+            Ok(device.running_config.clone())
+        }).await?
+    }
+
+    /// Running and startup config of the device, read from the device first when `refresh` is set.
+    pub async fn configs(&self, device_id: u32, refresh: bool, origin: Origin) -> Result<NetworkDevice, ExecutionError> {
+        if refresh {
+            return self.reload_configs(device_id, origin).await;
         }
+        self.get_device(device_id)
+    }
+
+    pub async fn save_config(&self, device_id: u32, origin: Origin) -> Result<NetworkDevice, ExecutionError> {
+        let backups = self.backup_handler.clone();
+        let trigger = origin.to_string();
+        self.run(device_id, origin, move |device| {
+            device.save_config()?;
+            archive(&backups, device, &trigger);
+            Ok(device.clone())
+        }).await?
+    }
+
+    /// Applies `changes` as one unit, rolling the device back when one of them fails.
+    pub async fn apply_changes(&self, device_id: u32, changes: Vec<DeviceChange>, confirm_minutes: Option<u32>, origin: Origin) -> Result<ChangesReport, ExecutionError> {
+        let backups = self.backup_handler.clone();
+        let trigger = origin.to_string();
+        self.run(device_id, origin, move |device| {
+            let report = device.apply_changes(&changes, confirm_minutes)?;
+            archive(&backups, device, &trigger);
+            Ok(report)
+        }).await?
     }
 
-    pub fn configure_interface(&mut self, device_id: u32, interface_id: u32, interface_dto: InterfaceDTO) -> Result<&mut NetworkDevice, ExecutionError> {
+    /// Keeps the last confirmed change of the device and saves it.
+    pub async fn confirm_changes(&self, device_id: u32, origin: Origin) -> Result<NetworkDevice, ExecutionError> {
+        let backups = self.backup_handler.clone();
+        let trigger = origin.to_string();
+        self.run(device_id, origin, move |device| {
+            device.confirm_changes()?;
+            archive(&backups, device, &trigger);
+            Ok(device.clone())
+        }).await?
+    }
+
+    /// Bridges the device console to `input` and `output` until `input` is closed or the session
+    /// goes idle. Operations queued meanwhile wait for the session to end.
+    pub async fn console_session(&self, device_id: u32, input: mpsc::Receiver<Vec<u8>>, output: UnboundedSender<Vec<u8>>, origin: Origin) -> Result<(), ExecutionError> {
+        let idle_timeout = self.console_idle_timeout;
+        self.run(device_id, origin, move |device| device.console_session(&input, &output, idle_timeout)).await?
+    }
+
+    /// CLI lines the changes would send, without touching the device.
+    pub fn changes_commands(&self, device_id: u32, changes: &[DeviceChange]) -> Result<Vec<String>, ExecutionError> {
         let device = self.get_device(device_id)?;
-        device.configure_interface(interface_id, interface_dto)
+        let mut commands = Vec::new();
+        for change in changes {
+            commands.extend(device.change_commands(change)?);
+        }
+        Ok(commands)
+    }
+
+    /// Applies an archived version or the given config text to the device.
+    pub async fn restore_config(&self, device_id: u32, restore: RestoreDTO, origin: Origin) -> Result<RestoreReport, ExecutionError> {
+        let config = self.restore_source(device_id, restore.version, restore.config)?;
+        let mode = restore.mode;
+        let backups = self.backup_handler.clone();
+        let trigger = origin.to_string();
+        self.run(device_id, origin, move |device| {
+            let report = device.restore_config(&config, mode)?;
+            archive(&backups, device, &trigger);
+            Ok(report)
+        }).await?
+    }
+
+    /// CLI lines the restore would send, without touching the device.
+    pub fn restore_commands(&self, device_id: u32, restore: RestoreDTO) -> Result<Vec<String>, ExecutionError> {
+        let config = self.restore_source(device_id, restore.version, restore.config)?;
+        self.get_device(device_id)?.restore_commands(&config, restore.mode)
     }
 
-    pub fn reload_configs(&mut self, device_id: u32) -> Result<&NetworkDevice, ExecutionError> {
-        let mut device = self.get_device(device_id)?;
+    fn restore_source(&self, device_id: u32, version: Option<String>, config: Option<String>) -> Result<String, ExecutionError> {
+        match (version, config) {
+            (Some(version), None) => self.backups()?.read(&self.get_device(device_id)?, &version),
+            (None, Some(config)) => Ok(config),
+            _ => Err(ExecutionError { message: "Either version or config has to be given.".to_string() }),
+        }
+    }
+}
 
-        device.read_running_config()?;
-        device.read_startup_config()?;
+/// Runs `job` with `session` set on the device, so its commands end up in the audit log, and
+/// recorded as one session when `recordings` is given.
+fn audited<R>(device: &mut NetworkDevice, session: Option<AuditSession>, recordings: Option<(RecordingHandler, Origin)>,
+              job: impl FnOnce(&mut NetworkDevice) -> R) -> R {
+    device.audit = session;
+    device.recording = recordings.map(|(recordings, origin)| recordings.session(device, &origin));
+    let result = job(device);
+    device.audit = None;
+    if let Some(recording) = device.recording.take() {
+        recording.finish();
+    }
+    result
+}
 
-        Ok(device)
+/// Archive failures are only logged, they shouldn't fail the operation that read the config.
+fn archive(backups: &Option<BackupHandler>, device: &NetworkDevice, trigger: &str) {
+    if let Some(backups) = backups {
+        if let Err(why) = backups.store_device(device, trigger) {
+            println!("Couldn't archive configs of {}: {}", device.hostname, why);
+        }
     }
-}
\ No newline at end of file
+}
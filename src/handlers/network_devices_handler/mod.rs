@@ -1,3 +1,4 @@
 pub mod model;
 pub mod function;
-pub mod endpoints;
\ No newline at end of file
+pub mod endpoints;
+pub mod worker;
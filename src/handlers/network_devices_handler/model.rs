@@ -1,9 +1,38 @@
 use std::collections::HashMap;
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::objects::device::model::NetworkDevice;
+use super::worker::DeviceWorker;
+use crate::handlers::audit_handler::model::AuditHandler;
+use crate::handlers::backup_handler::model::BackupHandler;
+use crate::handlers::recording_handler::model::RecordingHandler;
 
-#[derive(Clone,Serialize,Deserialize)]
+#[derive(Clone)]
 pub struct NetworkDevicesHandler {
-    pub devices: HashMap<u32, NetworkDevice>,
-}
\ No newline at end of file
+    pub(crate) devices: HashMap<u32, DeviceWorker>,
+    pub(crate) backup_handler: Option<BackupHandler>,
+    pub(crate) audit_handler: Option<AuditHandler>,
+    pub(crate) recording_handler: Option<RecordingHandler>,
+    /// Interactive console sessions end once nothing is typed for this long.
+    pub(crate) console_idle_timeout: Duration,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeviceLockQuery {
+    /// When false, answer 423 instead of queueing behind a running operation.
+    pub wait: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DryRunQuery {
+    /// When true, answer with the CLI lines the change would send instead of sending them.
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DryRunDTO {
+    pub commands: Vec<String>,
+}
@@ -0,0 +1,87 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use tokio::sync::oneshot;
+
+use crate::errors::execution_error::ExecutionError;
+use crate::objects::device::model::NetworkDevice;
+
+type Job = Box<dyn FnOnce(&mut NetworkDevice) + Send>;
+
+/// Owns a device on a dedicated thread and runs jobs against it one at a time, so the blocking
+/// console exchanges never run on the HTTP workers and each device only waits for itself.
+#[derive(Clone)]
+pub struct DeviceWorker {
+    jobs: mpsc::Sender<Job>,
+    /// Jobs queued or running on the device thread.
+    pending: Arc<AtomicUsize>,
+    /// Copy of the device as left by the last finished job, readable without queueing.
+    snapshot: Arc<RwLock<NetworkDevice>>,
+}
+
+impl DeviceWorker {
+    pub fn spawn(id: u32, mut device: NetworkDevice) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let pending = Arc::new(AtomicUsize::new(0));
+        let snapshot = Arc::new(RwLock::new(device.clone()));
+
+        let worker_pending = pending.clone();
+        let worker_snapshot = snapshot.clone();
+        thread::Builder::new()
+            .name(format!("device-{}", id))
+            .spawn(move || {
+                for job in queue {
+                    // the output parsers still panic on unexpected lines, that shouldn't take the device down
+                    if panic::catch_unwind(AssertUnwindSafe(|| job(&mut device))).is_err() {
+                        println!("Job on device {} panicked", id);
+                    }
+                    *worker_snapshot.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = device.clone();
+                    worker_pending.fetch_sub(1, Ordering::SeqCst);
+                }
+            })
+            .expect("Couldn't spawn device worker thread");
+
+        DeviceWorker { jobs, pending, snapshot }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.pending.load(Ordering::SeqCst) > 0
+    }
+
+    pub fn snapshot(&self) -> NetworkDevice {
+        self.snapshot.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// Queues `job` without waiting for its result.
+    pub fn submit<F>(&self, job: F) where F: FnOnce(&mut NetworkDevice) + Send + 'static {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        if self.jobs.send(Box::new(job)).is_err() {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Queues `job` and waits for the device thread to run it.
+    pub async fn run<F, R>(&self, job: F) -> Result<R, ExecutionError>
+        where F: FnOnce(&mut NetworkDevice) -> R + Send + 'static,
+              R: Send + 'static {
+        let (result_tx, result_rx) = oneshot::channel();
+        let snapshot = self.snapshot.clone();
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        if self.jobs.send(Box::new(move |device| {
+            let result = job(device);
+            // a read right after the result arrives has to see what the job did
+            *snapshot.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = device.clone();
+            let _ = result_tx.send(result);
+        })).is_err() {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            return Err(worker_stopped());
+        }
+
+        result_rx.await.map_err(|_| worker_stopped())
+    }
+}
+
+fn worker_stopped() -> ExecutionError {
+    ExecutionError { message: "Device worker stopped.".to_string() }
+}
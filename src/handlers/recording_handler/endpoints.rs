@@ -0,0 +1,44 @@
+use actix_web::{web, HttpResponse};
+use actix_web::web::{Data, Json};
+
+use crate::errors::execution_error::ExecutionError;
+use crate::handlers::auth_handler::model::Admin;
+use crate::handlers::network_devices_handler::model::NetworkDevicesHandler;
+use crate::handlers::recording_handler::model::RecordingInfo;
+
+#[utoipa::path(
+    get,
+    path = "/devices/{id}/recordings",
+    tag = "recordings",
+    params(("id" = u32, Path, description = "Device id")),
+    responses((status = 200, body = Vec<RecordingInfo>), (status = 403)),
+)]
+async fn list_recordings(_admin: Admin, path: web::Path<u32>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Json<Vec<RecordingInfo>>, ExecutionError> {
+    let id = path.into_inner();
+    let device = network_devices_handler.get_device(id)?;
+    let recordings = network_devices_handler.recordings()?.recordings(&device)?;
+    Ok(Json(recordings))
+}
+
+#[utoipa::path(
+    get,
+    path = "/devices/{id}/recordings/{recording}",
+    tag = "recordings",
+    params(("id" = u32, Path, description = "Device id"), ("recording" = String, Path)),
+    responses((status = 200, description = "Session in asciicast v2 format", body = String, content_type = "application/x-asciicast"), (status = 403)),
+)]
+async fn get_recording(_admin: Admin, path: web::Path<(u32, String)>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<HttpResponse, ExecutionError> {
+    let (id, recording) = path.into_inner();
+    let device = network_devices_handler.get_device(id)?;
+    let cast = network_devices_handler.recordings()?.read(&device, &recording)?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-asciicast")
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"{}.cast\"", recording)))
+        .body(cast))
+}
+
+/// Routes below `/api/v1`.
+pub fn init_rh_endpoints(cfg: &mut web::ServiceConfig) {
+    cfg.route("/devices/{id}/recordings", web::get().to(list_recordings));
+    cfg.route("/devices/{id}/recordings/{recording}", web::get().to(get_recording));
+}
@@ -0,0 +1,198 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use chrono::{DateTime, Utc};
+use log::warn;
+
+use super::model::*;
+use crate::errors::execution_error::ExecutionError;
+use crate::handlers::audit_handler::model::Origin;
+use crate::handlers::backup_handler::function::device_key;
+use crate::objects::device::model::NetworkDevice;
+
+const EXTENSION: &str = "cast";
+const CAST_VERSION: u8 = 2;
+/// Size of the terminal players replay the recordings in, the console itself has none.
+const WIDTH: u16 = 80;
+const HEIGHT: u16 = 24;
+const KEEP: usize = 200;
+const MAX_BYTES: u64 = 50 * 1024 * 1024;
+
+impl RecordingHandler {
+    pub fn new(recordings_loc: &str) -> Self {
+        RecordingHandler {
+            recordings_loc: PathBuf::from(recordings_loc),
+            keep: KEEP,
+            max_bytes: MAX_BYTES,
+        }
+    }
+
+    /// Keeps at most `keep` recordings and `max_bytes` of them per device.
+    pub fn with_retention(mut self, keep: usize, max_bytes: u64) -> Self {
+        self.keep = keep;
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Starts recording a session on the device for `origin`.
+    pub fn session(&self, device: &NetworkDevice, origin: &Origin) -> RecordingSession {
+        RecordingSession {
+            dir: self.device_dir(device),
+            keep: self.keep,
+            max_bytes: self.max_bytes,
+            origin: origin.clone(),
+            hostname: device.hostname.clone(),
+            started: Utc::now(),
+            clock: Instant::now(),
+            file: Default::default(),
+        }
+    }
+
+    /// Recordings of the device, oldest first.
+    pub fn recordings(&self, device: &NetworkDevice) -> Result<Vec<RecordingInfo>, ExecutionError> {
+        let dir = self.device_dir(device);
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(why) if why.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(why) => return Err(recording_error(&dir, why)),
+        };
+
+        let mut recordings: Vec<RecordingInfo> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != EXTENSION {
+                    return None;
+                }
+                let header = read_header(&path).ok()?;
+                Some(RecordingInfo {
+                    id: path.file_stem()?.to_str()?.to_string(),
+                    started: DateTime::from_timestamp(header.timestamp, 0)?,
+                    title: header.title,
+                    size: entry.metadata().ok()?.len(),
+                })
+            })
+            .collect();
+        recordings.sort_by(|a, b| a.started.cmp(&b.started).then_with(|| a.id.cmp(&b.id)));
+        Ok(recordings)
+    }
+
+    /// The recording as asciicast v2, a header line followed by one event per line.
+    pub fn read(&self, device: &NetworkDevice, recording_id: &str) -> Result<String, ExecutionError> {
+        if !is_recording_id(recording_id) {
+            return Err(ExecutionError { message: format!("{} is not a recording.", recording_id) });
+        }
+        let path = self.device_dir(device).join(format!("{}.{}", recording_id, EXTENSION));
+        fs::read_to_string(&path).map_err(|why| recording_error(&path, why))
+    }
+
+    /// Directory of the device, named like its config archive so renaming the device keeps its recordings.
+    pub fn device_dir(&self, device: &NetworkDevice) -> PathBuf {
+        self.recordings_loc.join(device_key(device))
+    }
+}
+
+impl RecordingSession {
+    /// Records what was sent to the console.
+    pub fn input(&self, data: &[u8]) {
+        self.record("i", data);
+    }
+
+    /// Records what the console printed.
+    pub fn output(&self, data: &[u8]) {
+        self.record("o", data);
+    }
+
+    /// Applies the retention limits again once the session is over, it may have grown past them.
+    pub fn finish(&self) {
+        let file = self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some((_, path)) = file.as_ref() {
+            if let Err(why) = self.prune(path) {
+                warn!("Couldn't prune recordings in {}: {}", self.dir.display(), why);
+            }
+        }
+    }
+
+    /// Appends one event, failures to write the recording are only logged.
+    fn record(&self, code: &str, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let event = serde_json::json!([self.clock.elapsed().as_secs_f64(), code, String::from_utf8_lossy(data)]);
+        let mut file = self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let result = match file.as_mut() {
+            Some((file, _)) => writeln!(file, "{}", event),
+            None => self.create().and_then(|(mut created, path)| {
+                writeln!(created, "{}", event)?;
+                let pruned = self.prune(&path);
+                *file = Some((created, path));
+                pruned
+            }),
+        };
+        if let Err(why) = result {
+            warn!("Couldn't record console session in {}: {}", self.dir.display(), why);
+        }
+    }
+
+    /// Creates the recording named after the session's start and writes its header.
+    fn create(&self) -> io::Result<(File, PathBuf)> {
+        fs::create_dir_all(&self.dir)?;
+        let name = self.started.format("%Y%m%dT%H%M%S%.6fZ").to_string();
+        let mut attempt = 0;
+        let mut file = loop {
+            let id = if attempt == 0 { name.clone() } else { format!("{}-{}", name, attempt) };
+            let path = self.dir.join(format!("{}.{}", id, EXTENSION));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Err(why) if why.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+                result => break (result?, path),
+            }
+        };
+        let header = CastHeader {
+            version: CAST_VERSION,
+            width: WIDTH,
+            height: HEIGHT,
+            timestamp: self.started.timestamp(),
+            title: format!("{}: {}", self.hostname, self.origin),
+        };
+        writeln!(file.0, "{}", serde_json::to_string(&header)?)?;
+        Ok(file)
+    }
+
+    /// Removes the oldest recordings of the device over its limits, never `current`.
+    fn prune(&self, current: &Path) -> io::Result<()> {
+        let mut recordings: Vec<(PathBuf, u64)> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|extension| extension == EXTENSION))
+            .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?.len())))
+            .collect();
+        // the names start with the time the session started
+        recordings.sort();
+        let mut total: u64 = recordings.iter().map(|(_, size)| size).sum();
+        let mut count = recordings.len();
+        for (path, size) in recordings {
+            if (count <= self.keep && total <= self.max_bytes) || path == current {
+                break;
+            }
+            fs::remove_file(&path)?;
+            count -= 1;
+            total -= size;
+        }
+        Ok(())
+    }
+}
+
+fn read_header(path: &Path) -> io::Result<CastHeader> {
+    let mut line = String::new();
+    BufReader::new(File::open(path)?).read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// Recording ids are timestamps with an optional counter, anything else could leave the device's directory.
+fn is_recording_id(id: &str) -> bool {
+    !id.is_empty() && !id.starts_with('.') && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+}
+
+fn recording_error(path: &Path, why: io::Error) -> ExecutionError {
+    ExecutionError { message: format!("Couldn't access recording {}: {}", path.display(), why) }
+}
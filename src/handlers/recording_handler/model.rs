@@ -0,0 +1,56 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::handlers::audit_handler::model::Origin;
+
+/// Console sessions in asciicast v2 format, one `.cast` file per session in a directory per
+/// device under `recordings_loc`. The oldest recordings of a device are removed once it has more
+/// than `keep` of them or they take more than `max_bytes`.
+#[derive(Debug, Clone)]
+pub struct RecordingHandler {
+    pub(crate) recordings_loc: PathBuf,
+    pub(crate) keep: usize,
+    pub(crate) max_bytes: u64,
+}
+
+/// Set on a device while it runs a job, so everything sent to and printed by its console is
+/// recorded. The file is only created with the first event, jobs that don't touch the console
+/// leave no recording behind.
+#[derive(Debug, Clone)]
+pub struct RecordingSession {
+    pub(crate) dir: PathBuf,
+    pub(crate) keep: usize,
+    pub(crate) max_bytes: u64,
+    pub(crate) origin: Origin,
+    pub(crate) hostname: String,
+    pub(crate) started: DateTime<Utc>,
+    pub(crate) clock: Instant,
+    /// The recording and its path, once created.
+    pub(crate) file: Arc<Mutex<Option<(File, PathBuf)>>>,
+}
+
+/// First line of an asciicast v2 file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CastHeader {
+    pub version: u8,
+    pub width: u16,
+    pub height: u16,
+    /// Unix time the session started at.
+    pub timestamp: i64,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RecordingInfo {
+    /// File name without extension, used to download the recording.
+    pub id: String,
+    pub started: DateTime<Utc>,
+    /// Endpoint and caller the session ran for.
+    pub title: String,
+    pub size: u64,
+}
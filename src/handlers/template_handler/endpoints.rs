@@ -0,0 +1,81 @@
+use actix_web::{web, Either, Error};
+use actix_web::http::Method;
+use actix_web::web::{Data, Json, Query};
+
+use crate::handlers::deprecated;
+use crate::errors::execution_error::ExecutionError;
+use crate::handlers::audit_handler::model::Origin;
+use crate::handlers::auth_handler::model::Admin;
+use crate::handlers::network_devices_handler::endpoints::{check_lock, is_dry_run};
+use crate::handlers::network_devices_handler::model::{DeviceLockQuery, DryRunDTO, DryRunQuery, NetworkDevicesHandler};
+use crate::handlers::template_handler::model::{ApplyTemplateDTO, RenderDTO, RenderedTemplateDTO, TemplateHandler};
+use crate::objects::config::model::{RestoreDTO, RestoreMode, RestoreReport};
+
+#[utoipa::path(get, path = "/templates", tag = "templates", responses((status = 200, body = Vec<String>)))]
+async fn list_templates(template_handler: Data<TemplateHandler>) -> Json<Vec<String>> {
+    Json(template_handler.names())
+}
+
+#[utoipa::path(
+    post,
+    path = "/templates/{name}/render",
+    tag = "templates",
+    params(("name" = String, Path)),
+    request_body = RenderDTO,
+    responses((status = 200, body = RenderedTemplateDTO)),
+)]
+async fn render_template(path: web::Path<String>, render: Json<RenderDTO>, template_handler: Data<TemplateHandler>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Json<RenderedTemplateDTO>, ExecutionError> {
+    let name = path.into_inner();
+    let device = match render.device_id {
+        Some(id) => Some(network_devices_handler.get_device(id)?),
+        None => None,
+    };
+    let config = template_handler.render(&name, &render.variables, device.as_ref())?;
+    Ok(Json(RenderedTemplateDTO { name, config }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/devices/{id}/templates/{name}",
+    tag = "templates",
+    params(("id" = u32, Path, description = "Device id"), ("name" = String, Path), DeviceLockQuery, DryRunQuery),
+    request_body = ApplyTemplateDTO,
+    responses(
+        (status = 200, body = RestoreReport),
+        (status = 200, description = "Dry run", body = DryRunDTO),
+        (status = 403),
+        (status = 423),
+    ),
+)]
+#[allow(clippy::too_many_arguments)]
+async fn apply_template(_admin: Admin, origin: Origin, path: web::Path<(u32, String)>, lock: Query<DeviceLockQuery>, dry_run: Query<DryRunQuery>, apply: Json<ApplyTemplateDTO>, template_handler: Data<TemplateHandler>, network_devices_handler: Data<NetworkDevicesHandler>) -> Result<Either<Json<DryRunDTO>, Json<RestoreReport>>, Error> {
+    let (id, name) = path.into_inner();
+    let device = network_devices_handler.get_device(id)?;
+    let config = template_handler.render(&name, &apply.variables, Some(&device))?;
+    let restore = RestoreDTO {
+        version: None,
+        config: Some(config),
+        mode: RestoreMode::Merge,
+    };
+    if is_dry_run(&dry_run) {
+        let commands = network_devices_handler.restore_commands(id, restore)?;
+        return Ok(Either::Left(Json(DryRunDTO { commands })));
+    }
+    check_lock(&network_devices_handler, id, &lock)?;
+    let report = network_devices_handler.restore_config(id, restore, origin).await?;
+    Ok(Either::Right(Json(report)))
+}
+
+/// Routes below `/api/v1`.
+pub fn init_th_endpoints(cfg: &mut web::ServiceConfig) {
+    cfg.route("/templates", web::get().to(list_templates));
+    cfg.route("/templates/{name}/render", web::post().to(render_template));
+    cfg.route("/devices/{id}/templates/{name}", web::post().to(apply_template));
+}
+
+/// Unversioned routes from before `/api/v1`, kept working for existing clients.
+pub fn init_th_legacy_endpoints(cfg: &mut web::ServiceConfig) {
+    cfg.service(deprecated("/templates", Method::GET, list_templates));
+    cfg.service(deprecated("/templates/{name}/render", Method::POST, render_template));
+    cfg.service(deprecated("/device/{id}/template/{name}", Method::POST, apply_template));
+}
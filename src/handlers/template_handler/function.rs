@@ -0,0 +1,48 @@
+use handlebars::Handlebars;
+use serde_json::{json, Value};
+
+use super::model::TemplateHandler;
+use crate::errors::execution_error::ExecutionError;
+use crate::objects::device::model::NetworkDevice;
+
+const EXTENSION: &str = ".hbs";
+
+impl TemplateHandler {
+    pub fn new(handlebars: Handlebars<'static>) -> Self {
+        TemplateHandler { handlebars }
+    }
+
+    /// Loads every template in `config_templates_loc`, a missing directory just means no templates.
+    pub fn load(config_templates_loc: &str) -> Result<Self, ExecutionError> {
+        let mut handlebars = Handlebars::new();
+        // configs aren't html, and a typo in a variable name should fail instead of leaving a gap
+        handlebars.register_escape_fn(handlebars::no_escape);
+        handlebars.set_strict_mode(true);
+        handlebars.register_templates_directory(EXTENSION, config_templates_loc)
+            .map_err(|why| ExecutionError { message: format!("Couldn't load config templates: {}", why) })?;
+        Ok(TemplateHandler::new(handlebars))
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.handlebars.get_templates().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Renders template `name` with `variables` at the top level and the device, if any, as `device`.
+    pub fn render(&self, name: &str, variables: &Value, device: Option<&NetworkDevice>) -> Result<String, ExecutionError> {
+        if !self.handlebars.has_template(name) {
+            return Err(ExecutionError { message: format!("Couldn't find template {}.", name) });
+        }
+        let mut data = match variables {
+            Value::Object(variables) => variables.clone(),
+            Value::Null => Default::default(),
+            _ => return Err(ExecutionError { message: "Template variables have to be an object.".to_string() }),
+        };
+        if let Some(device) = device {
+            data.insert("device".to_string(), json!(device));
+        }
+        self.handlebars.render(name, &data)
+            .map_err(|why| ExecutionError { message: format!("Couldn't render template {}: {}", name, why) })
+    }
+}
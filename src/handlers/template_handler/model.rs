@@ -0,0 +1,30 @@
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// Named device config templates, `{name}.hbs` files in `config_templates_loc`.
+#[derive(Clone)]
+pub struct TemplateHandler {
+    pub(crate) handlebars: Handlebars<'static>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RenderDTO {
+    #[serde(default)]
+    pub variables: Value,
+    /// Makes the device available to the template as `device`.
+    pub device_id: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ApplyTemplateDTO {
+    #[serde(default)]
+    pub variables: Value,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RenderedTemplateDTO {
+    pub name: String,
+    pub config: String,
+}
@@ -0,0 +1,19 @@
+use actix_web::web;
+use actix_web::web::{Data, Json};
+
+use crate::handlers::terminal_handler::model::{TerminalHandler, TerminalPort};
+
+#[utoipa::path(
+    get,
+    path = "/terminals",
+    tag = "devices",
+    responses((status = 200, description = "TCP ports serving the device consoles, empty while the terminal server is off", body = Vec<TerminalPort>)),
+)]
+async fn list_terminals(terminal_handler: Data<TerminalHandler>) -> Json<Vec<TerminalPort>> {
+    Json(terminal_handler.ports().to_vec())
+}
+
+/// Routes below `/api/v1`.
+pub fn init_te_endpoints(cfg: &mut web::ServiceConfig) {
+    cfg.route("/terminals", web::get().to(list_terminals));
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::pin;
+use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use actix_web::web::Data;
+use log::{error, info, warn};
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::unbounded_channel;
+
+use super::model::{TerminalHandler, TerminalMode, TerminalPort};
+use super::telnet::Telnet;
+use crate::handlers::audit_handler::model::Origin;
+use crate::handlers::auth_handler::model::{AuthHandler, Role};
+use crate::handlers::config_handler::model::ConfigHandler;
+use crate::handlers::network_devices_handler::model::NetworkDevicesHandler;
+
+/// How long a client gets to send its token.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_TOKEN_LENGTH: usize = 256;
+/// Failed logins an address may have within `FAILED_LOGIN_WINDOW` before it's refused.
+const MAX_FAILED_LOGINS: usize = 5;
+const FAILED_LOGIN_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// Times of the recent failed logins per client address, shared by all terminal ports.
+type FailedLogins = Arc<Mutex<HashMap<IpAddr, Vec<Instant>>>>;
+
+/// What the sessions on one device's port share.
+struct Terminal {
+    device_id: u32,
+    mode: TerminalMode,
+    devices_handler: Data<NetworkDevicesHandler>,
+    auth_handler: Data<AuthHandler>,
+    in_use: Arc<AtomicBool>,
+    failed_logins: FailedLogins,
+}
+
+impl Terminal {
+    fn locked_out(&self, address: IpAddr) -> bool {
+        let mut failed_logins = self.failed_logins.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        failed_logins.retain(|_, failures| {
+            failures.retain(|failure| failure.elapsed() < FAILED_LOGIN_WINDOW);
+            !failures.is_empty()
+        });
+        failed_logins.get(&address).is_some_and(|failures| failures.len() >= MAX_FAILED_LOGINS)
+    }
+
+    fn login_failed(&self, address: IpAddr) {
+        let mut failed_logins = self.failed_logins.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        failed_logins.entry(address).or_default().push(Instant::now());
+    }
+}
+
+/// Frees the device's port for the next session when a session ends, however it ends.
+struct InUse(Arc<AtomicBool>);
+
+impl Drop for InUse {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+impl TerminalHandler {
+    /// Binds a port for every device on every address of `config` and accepts sessions on
+    /// them in the background, so it has to be called within a Tokio runtime.
+    pub fn start(config: &ConfigHandler, devices_handler: Data<NetworkDevicesHandler>, auth_handler: Data<AuthHandler>) -> io::Result<Self> {
+        let mut device_ids: Vec<u32> = devices_handler.devices.keys().copied().collect();
+        device_ids.sort();
+
+        let mut terminal_handler = TerminalHandler::default();
+        let failed_logins = FailedLogins::default();
+        for device_id in device_ids {
+            let port = terminal_port(config.terminal_base_port, device_id)?;
+            let terminal = Arc::new(Terminal {
+                device_id,
+                mode: config.terminal_mode,
+                devices_handler: devices_handler.clone(),
+                auth_handler: auth_handler.clone(),
+                in_use: Arc::new(AtomicBool::new(false)),
+                failed_logins: failed_logins.clone(),
+            });
+            let mut addresses = Vec::new();
+            for address in &config.terminal_bind_addresses {
+                let listener = std::net::TcpListener::bind((address.as_str(), port))?;
+                listener.set_nonblocking(true)?;
+                addresses.push(listener.local_addr()?);
+                tokio::spawn(accept(TcpListener::from_std(listener)?, terminal.clone()));
+            }
+            info!("Console of device {} is served on {:?}", device_id, addresses);
+            terminal_handler.ports.push(TerminalPort { device_id, mode: config.terminal_mode, addresses });
+        }
+        Ok(terminal_handler)
+    }
+
+    pub fn ports(&self) -> &[TerminalPort] {
+        &self.ports
+    }
+}
+
+/// Device `id` listens on `base + id`, a base of 0 lets the OS pick the ports.
+fn terminal_port(base: u16, device_id: u32) -> io::Result<u16> {
+    if base == 0 {
+        return Ok(0);
+    }
+    u16::try_from(device_id).ok()
+        .and_then(|device_id| base.checked_add(device_id))
+        .ok_or(io::Error::new(io::ErrorKind::InvalidInput, format!("No terminal port left for device {} above {}", device_id, base)))
+}
+
+async fn accept(listener: TcpListener, terminal: Arc<Terminal>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                let terminal = terminal.clone();
+                tokio::spawn(async move {
+                    if let Err(why) = session(stream, peer, &terminal).await {
+                        warn!("Terminal session of {} on device {} failed: {}", peer, terminal.device_id, why);
+                    }
+                });
+            }
+            Err(why) => error!("Couldn't accept terminal session for device {}: {}", terminal.device_id, why),
+        }
+    }
+}
+
+/// Logs the client in and bridges it to the console until either side hangs up.
+async fn session(mut stream: TcpStream, peer: SocketAddr, terminal: &Terminal) -> io::Result<()> {
+    let mut telnet = match terminal.mode {
+        TerminalMode::Telnet => Some(Telnet::default()),
+        TerminalMode::Raw => None,
+    };
+    if let Some(telnet) = &mut telnet {
+        stream.write_all(&telnet.greeting()).await?;
+    }
+
+    if terminal.locked_out(peer.ip()) {
+        warn!("Refused terminal login from {} on device {}, too many failed logins", peer, terminal.device_id);
+        return stream.write_all(b"Too many failed logins, try again later.\r\n").await;
+    }
+    let Some(caller) = login(&mut stream, peer, &mut telnet, &terminal.auth_handler).await? else {
+        warn!("Failed terminal login from {} on device {}", peer, terminal.device_id);
+        terminal.login_failed(peer.ip());
+        return stream.write_all(b"Access denied.\r\n").await;
+    };
+    if terminal.in_use.swap(true, Ordering::SeqCst) {
+        return stream.write_all(b"Console is in use by another terminal session.\r\n").await;
+    }
+    let _in_use = InUse(terminal.in_use.clone());
+    if terminal.devices_handler.check_available(terminal.device_id).is_err() {
+        stream.write_all(b"Waiting for the device to finish an API operation...\r\n").await?;
+    }
+
+    let origin = Origin {
+        caller,
+        endpoint: format!("terminal {}", stream.local_addr()?),
+    };
+    let (mut reader, mut writer) = stream.split();
+    let (input_tx, input_rx) = mpsc::channel();
+    let (output_tx, mut output) = unbounded_channel();
+    let mut console = pin!(terminal.devices_handler.console_session(terminal.device_id, input_rx, output_tx, origin));
+    let mut input = Some(input_tx);
+    let mut buffer = [0u8; 1024];
+    let result = loop {
+        tokio::select! {
+            result = &mut console => break result,
+            Some(printed) = output.recv() => {
+                if writer.write_all(&encode(&telnet, &printed)).await.is_err() {
+                    input = None;
+                }
+            }
+            read = reader.read(&mut buffer), if input.is_some() => match read {
+                Ok(0) | Err(_) => input = None,
+                Ok(read) => {
+                    let data = decode(&mut telnet, &mut writer, &buffer[..read]).await?;
+                    if data.is_empty() {
+                        continue;
+                    }
+                    if input.as_ref().is_some_and(|input| input.send(data).is_err()) {
+                        input = None;
+                    }
+                }
+            },
+        }
+    };
+
+    while let Ok(printed) = output.try_recv() {
+        writer.write_all(&encode(&telnet, &printed)).await?;
+    }
+    if let Err(why) = result {
+        writer.write_all(format!("\r\n{}\r\n", why.message).as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// Name the session is audited as: the token's name, or the client address when
+/// authentication is off. `None` when the client didn't send an admin token.
+async fn login(stream: &mut TcpStream, peer: SocketAddr, telnet: &mut Option<Telnet>, auth_handler: &AuthHandler) -> io::Result<Option<String>> {
+    if !auth_handler.is_enabled() {
+        return Ok(Some(peer.ip().to_string()));
+    }
+    stream.write_all(b"Token: ").await?;
+    let token = match tokio::time::timeout(LOGIN_TIMEOUT, read_line(stream, telnet)).await {
+        Ok(token) => token?,
+        Err(_) => return Ok(None),
+    };
+    stream.write_all(b"\r\n").await?;
+    Ok(auth_handler.identify(token.trim())
+        .filter(|identity| identity.role >= Role::Admin)
+        .map(|identity| identity.name))
+}
+
+/// Reads up to the end of the line, the Telnet client doesn't echo it because the agent
+/// announced it echoes itself.
+async fn read_line(stream: &mut TcpStream, telnet: &mut Option<Telnet>) -> io::Result<String> {
+    let mut line = Vec::new();
+    let mut buffer = [0u8; 256];
+    loop {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let data = decode(telnet, stream, &buffer[..read]).await?;
+        if let Some(end) = data.iter().position(|&byte| byte == b'\r' || byte == b'\n') {
+            line.extend_from_slice(&data[..end]);
+            return Ok(String::from_utf8_lossy(&line).to_string());
+        }
+        line.extend_from_slice(&data);
+        if line.len() > MAX_TOKEN_LENGTH {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Token is too long"));
+        }
+    }
+}
+
+/// Console input in `received`, answering the Telnet negotiation it contains.
+async fn decode(telnet: &mut Option<Telnet>, writer: &mut (impl AsyncWrite + Unpin), received: &[u8]) -> io::Result<Vec<u8>> {
+    match telnet {
+        Some(telnet) => {
+            let decoded = telnet.decode(received);
+            if !decoded.replies.is_empty() {
+                writer.write_all(&decoded.replies).await?;
+            }
+            Ok(decoded.data)
+        }
+        None => Ok(received.to_vec()),
+    }
+}
+
+fn encode(telnet: &Option<Telnet>, printed: &[u8]) -> Vec<u8> {
+    match telnet {
+        Some(_) => Telnet::encode(printed),
+        None => printed.to_vec(),
+    }
+}
@@ -0,0 +1,4 @@
+pub mod model;
+pub mod function;
+pub mod telnet;
+pub mod endpoints;
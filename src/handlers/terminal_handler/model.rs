@@ -0,0 +1,32 @@
+use std::net::SocketAddr;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Exposes the device consoles on TCP ports like a terminal server, device `id` listens on
+/// `terminal_base_port + id` of every terminal bind address. One session per device at a time.
+///
+/// Telnet and raw TCP aren't encrypted, the admin token typed at the login crosses the network
+/// in cleartext. Failed logins are logged and an address that fails too often is refused for a
+/// while.
+#[derive(Debug, Clone, Default)]
+pub struct TerminalHandler {
+    pub(crate) ports: Vec<TerminalPort>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TerminalMode {
+    /// Bytes are passed through untouched, like `ser2net` raw ports.
+    Raw,
+    /// Telnet with the RFC 2217 com port option, so clients can query the line settings.
+    #[default]
+    Telnet,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TerminalPort {
+    pub device_id: u32,
+    pub mode: TerminalMode,
+    #[schema(value_type = Vec<String>)]
+    pub addresses: Vec<SocketAddr>,
+}
@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+
+use crate::objects::console::model::BAUD_RATE;
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+/// RFC 2217 com port control.
+const COM_PORT: u8 = 44;
+
+/// RFC 2217 client commands, the server answers each with the command plus 100.
+const SIGNATURE: u8 = 0;
+const SET_BAUDRATE: u8 = 1;
+const SET_DATASIZE: u8 = 2;
+const SET_PARITY: u8 = 3;
+const SET_STOPSIZE: u8 = 4;
+const SET_CONTROL: u8 = 5;
+/// Highest client command, PURGE-DATA.
+const LAST_COMMAND: u8 = 12;
+const SERVER_OFFSET: u8 = 100;
+/// Longest subnegotiation kept, COM-PORT requests need a handful of bytes.
+const MAX_SUBNEGOTIATION: usize = 32;
+
+/// Line settings reported to RFC 2217 clients: 8 data bits, no parity, 1 stop bit, no flow control.
+const DATASIZE: u8 = 8;
+const PARITY_NONE: u8 = 1;
+const STOPSIZE_ONE: u8 = 1;
+const CONTROL_NO_FLOW: u8 = 1;
+const CONTROL_BREAK_OFF: u8 = 6;
+const CONTROL_DTR_ON: u8 = 8;
+const CONTROL_RTS_ON: u8 = 11;
+const CONTROL_NO_INBOUND_FLOW: u8 = 14;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum State {
+    #[default]
+    Data,
+    /// Previous byte was a carriage return, a following NUL or LF belongs to it.
+    Cr,
+    Iac,
+    Negotiation(u8),
+    Subnegotiation,
+    SubnegotiationIac,
+}
+
+/// Telnet server side of a terminal session. The agent owns the serial line, so RFC 2217
+/// requests to change its settings are answered with the settings it keeps using.
+#[derive(Debug, Default)]
+pub struct Telnet {
+    state: State,
+    subnegotiation: Vec<u8>,
+    /// The subnegotiation outgrew `MAX_SUBNEGOTIATION` and is dropped at its end.
+    oversized: bool,
+    /// Options the agent agreed to perform.
+    local: HashSet<u8>,
+    /// Options the client agreed to perform.
+    remote: HashSet<u8>,
+}
+
+/// Console input and the negotiation answers for the client, split from the received bytes.
+#[derive(Debug, Default)]
+pub struct Decoded {
+    pub data: Vec<u8>,
+    pub replies: Vec<u8>,
+}
+
+impl Telnet {
+    /// Sent on connect, the device echoes the input itself and nobody needs go aheads.
+    pub fn greeting(&mut self) -> Vec<u8> {
+        self.local.extend([ECHO, SUPPRESS_GO_AHEAD]);
+        vec![IAC, WILL, ECHO, IAC, WILL, SUPPRESS_GO_AHEAD]
+    }
+
+    /// Escapes console output for the client.
+    pub fn encode(data: &[u8]) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(data.len());
+        for &byte in data {
+            if byte == IAC {
+                encoded.push(IAC);
+            }
+            encoded.push(byte);
+        }
+        encoded
+    }
+
+    pub fn decode(&mut self, received: &[u8]) -> Decoded {
+        let mut decoded = Decoded::default();
+        for &byte in received {
+            self.state = match (self.state, byte) {
+                (State::Cr, 0 | b'\n') => State::Data,
+                (State::Data | State::Cr, IAC) => State::Iac,
+                (State::Data | State::Cr, _) => {
+                    decoded.data.push(byte);
+                    if byte == b'\r' { State::Cr } else { State::Data }
+                }
+                (State::Iac, IAC) => {
+                    decoded.data.push(IAC);
+                    State::Data
+                }
+                (State::Iac, WILL | WONT | DO | DONT) => State::Negotiation(byte),
+                (State::Iac, SB) => {
+                    self.subnegotiation.clear();
+                    self.oversized = false;
+                    State::Subnegotiation
+                }
+                // NOP, break, are you there and friends have no meaning for the serial line
+                (State::Iac, _) => State::Data,
+                (State::Negotiation(command), option) => {
+                    self.negotiate(command, option, &mut decoded.replies);
+                    State::Data
+                }
+                (State::Subnegotiation, IAC) => State::SubnegotiationIac,
+                (State::SubnegotiationIac, SE) => {
+                    if !self.oversized {
+                        self.subnegotiate(&mut decoded.replies);
+                    }
+                    State::Data
+                }
+                (State::Subnegotiation | State::SubnegotiationIac, _) => {
+                    self.collect(byte);
+                    State::Subnegotiation
+                }
+            };
+        }
+        decoded
+    }
+
+    /// Answers option requests, only when they change the option's state so both sides
+    /// don't keep acknowledging each other.
+    fn negotiate(&mut self, command: u8, option: u8, replies: &mut Vec<u8>) {
+        let reply = match command {
+            DO if self.local.contains(&option) => None,
+            DO if [ECHO, SUPPRESS_GO_AHEAD].contains(&option) => {
+                self.local.insert(option);
+                Some(WILL)
+            }
+            DO => Some(WONT),
+            DONT => self.local.remove(&option).then_some(WONT),
+            WILL if self.remote.contains(&option) => None,
+            WILL if [SUPPRESS_GO_AHEAD, COM_PORT].contains(&option) => {
+                self.remote.insert(option);
+                Some(DO)
+            }
+            WILL => Some(DONT),
+            _ => self.remote.remove(&option).then_some(DONT),
+        };
+        if let Some(reply) = reply {
+            replies.extend([IAC, reply, option]);
+        }
+    }
+
+    fn collect(&mut self, byte: u8) {
+        if self.subnegotiation.len() < MAX_SUBNEGOTIATION {
+            self.subnegotiation.push(byte);
+        } else {
+            self.oversized = true;
+        }
+    }
+
+    fn subnegotiate(&mut self, replies: &mut Vec<u8>) {
+        let Some((&option, request)) = self.subnegotiation.split_first() else { return };
+        let Some((&command, value)) = request.split_first() else { return };
+        if option != COM_PORT || !self.remote.contains(&COM_PORT) || command > LAST_COMMAND {
+            return;
+        }
+        let value = match command {
+            SIGNATURE => b"rpi_client".to_vec(),
+            SET_BAUDRATE => BAUD_RATE.to_be_bytes().to_vec(),
+            SET_DATASIZE => vec![DATASIZE],
+            SET_PARITY => vec![PARITY_NONE],
+            SET_STOPSIZE => vec![STOPSIZE_ONE],
+            SET_CONTROL => vec![control_reply(value.first().copied().unwrap_or(0))],
+            // line state masks, flow suspends and purges are acknowledged as requested
+            _ => value.to_vec(),
+        };
+        replies.extend([IAC, SB, COM_PORT, command + SERVER_OFFSET]);
+        replies.extend(Telnet::encode(&value));
+        replies.extend([IAC, SE]);
+    }
+}
+
+/// State reported for a SET-CONTROL request. Flow control is always off, break is never sent
+/// and DTR and RTS stay on, whatever the client asked for.
+fn control_reply(requested: u8) -> u8 {
+    match requested {
+        0..=3 => CONTROL_NO_FLOW,
+        4..=6 => CONTROL_BREAK_OFF,
+        7..=9 => CONTROL_DTR_ON,
+        10..=12 => CONTROL_RTS_ON,
+        13..=16 => CONTROL_NO_INBOUND_FLOW,
+        _ => requested,
+    }
+}
@@ -2,67 +2,54 @@ mod not_found;
 mod errors;
 mod objects;
 mod handlers;
-
-use handlers::network_devices_handler::model::NetworkDevicesHandler;
-use handlers::network_devices_handler::endpoints::init_nd_endpoints;
-use handlers::config_handler::model::ConfigHandler;
-use handlers::config_handler::endpoints::init_ch_endpoints;
-
-use std::{env, fs, u32};
-use std::collections::HashMap;
-use std::sync::Mutex;
-use actix_web::{get, HttpServer, App, web, Responder, post, HttpResponse, ResponseError, delete};
-use actix_web::error::JsonPayloadError;
+mod server;
+mod tls;
+mod openapi;
+
+pub use handlers::network_devices_handler::model::NetworkDevicesHandler;
+pub use handlers::config_handler::model::ConfigHandler;
+pub use handlers::config_handler::function::app_specific_uuid;
+pub use handlers::backup_handler::model::BackupHandler;
+pub use handlers::audit_handler::model::AuditHandler;
+pub use handlers::recording_handler::model::RecordingHandler;
+pub use handlers::auth_handler::model::{ApiToken, AuthHandler, Role};
+pub use handlers::template_handler::model::TemplateHandler;
+pub use objects::device::model::NetworkDevice;
+pub use objects::interface::model::Interface;
+pub use objects::console::model::{Console, ConsoleHandle};
+pub use handlers::terminal_handler::telnet::{Decoded, Telnet};
+pub use server::ServerBuilder;
+pub use openapi::ApiDoc;
+
+use std::env;
+use std::net::SocketAddr;
+use actix_web::{get, Responder, HttpResponse};
 use actix_web::http::StatusCode;
-use actix_web::middleware::Logger;
-use actix_web::web::{Data, Json};
 use actix_web::dev::Server;
 use dotenv::dotenv;
-use env_logger::Env;
-use handlebars::Handlebars;
-use log::info;
 
+#[utoipa::path(tag = "agent", security(), responses((status = 200)))]
 #[get("/status/health")]
-async fn health() -> impl Responder {
+pub async fn health() -> impl Responder {
     HttpResponse::new(StatusCode::OK)
 }
 
-pub fn run() -> std::io::Result<Server>{
+pub fn run() -> std::io::Result<(Server, Vec<SocketAddr>)>{
 
-    // dotenv().ok();
+    dotenv().ok();
     env::set_var("RUST_LOG", "rpi_client=info");
-    env_logger::init();
-
-    // let paths = fs::read_dir("./").unwrap();
-    // for path in paths {
-    //     println!("Name: {}", path.unwrap().path().display())
-    // }
-
-    // println!("{:?}", env::current_dir());
+    let _ = env_logger::try_init();
 
-    let mut devices_handler = NetworkDevicesHandler::default();
-    devices_handler.read_interfaces();
-    devices_handler.read_vlans();
-    let mut conf = ConfigHandler::init(&Default::default());
-    let templates = conf.templates_loc.clone();
-    let ip_addr = conf.ip_address.clone();
-
-    let mut handlebars = Handlebars::new();
-    handlebars.register_templates_directory(".html", templates).expect("Couldn't load templates");
+    run_with_config(ConfigHandler::init(&Default::default())?)
+}
 
-    let server = HttpServer::new(move || {
-        App::new()
-            .app_data(Data::new(handlebars.clone()))
-            .app_data(Data::new(conf.clone()))
-            .app_data(Data::new(Mutex::new(devices_handler.clone())))
-            .wrap(Logger::default())
-            .service(health)
-            .configure(init_nd_endpoints)
-            .configure(init_ch_endpoints)
-            .default_service(web::route().to(not_found::not_found))
-    })
-        .bind((ip_addr.to_owned(), 8080))?
-        .run();
+/// Discovers the devices attached to the serial ports and starts the agent with the given config.
+pub fn run_with_config(conf: ConfigHandler) -> std::io::Result<(Server, Vec<SocketAddr>)>{
+    let devices_handler = NetworkDevicesHandler::default()
+        .with_backups(BackupHandler::new(&conf.backup_loc).with_git(conf.backup_git))
+        .with_audit(AuditHandler::new(&conf.audit_loc))
+        .with_recordings(RecordingHandler::new(&conf.recordings_loc));
+    devices_handler.refresh();
 
-    Ok(server)
-}
\ No newline at end of file
+    ServerBuilder::new(conf, devices_handler).build()
+}
@@ -1,5 +1,8 @@
+use log::info;
 use rpi_client::run;
 #[actix_web::main]
 async fn main() -> std::io::Result<()>{
-    run()?.await
-}
\ No newline at end of file
+    let (server, addrs) = run()?;
+    info!("Listening on {:?}", addrs);
+    server.await
+}
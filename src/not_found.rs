@@ -1,4 +1,4 @@
-use actix_web::{http, HttpRequest, HttpResponse, Responder, web};
+use actix_web::{http, HttpRequest, HttpResponse, Responder};
 use actix_web::web::Data;
 use handlebars::Handlebars;
 use serde_json::json;
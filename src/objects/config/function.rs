@@ -0,0 +1,267 @@
+use similar::{ChangeTag, TextDiff};
+
+use super::model::*;
+use crate::errors::execution_error::ExecutionError;
+
+/// Lines IOS prints around the configuration itself.
+const PREAMBLE: [&str; 3] = ["Building configuration", "Current configuration", "Using "];
+
+impl ParsedConfig {
+    pub fn parse(text: &str) -> Self {
+        let mut config = ParsedConfig::default();
+        // (indent, header) of the top level line the following indented lines belong to
+        let mut current: Option<(String, Vec<(usize, String)>)> = None;
+        // delimiter of the banner being read, its lines are kept as they are until it shows up again
+        let mut banner: Option<String> = None;
+
+        for line in text.lines() {
+            let trimmed = line.trim_end();
+            if let Some(delimiter) = &banner {
+                if trimmed.contains(delimiter.as_str()) {
+                    banner = None;
+                }
+                if let Some((_, children)) = current.as_mut() {
+                    children.push((1, trimmed.to_string()));
+                }
+                continue;
+            }
+            let content = trimmed.trim_start();
+            if content.is_empty() || content.starts_with('!') || content == "end" || PREAMBLE.iter().any(|p| content.starts_with(p)) {
+                continue;
+            }
+            let indent = trimmed.len() - content.len();
+            if indent == 0 {
+                if let Some((header, children)) = current.take() {
+                    config.push(header, children);
+                }
+                current = Some((content.to_string(), Vec::new()));
+                banner = open_banner(content);
+            } else if let Some((_, children)) = current.as_mut() {
+                children.push((indent, content.to_string()));
+            } else {
+                config.global.push(content.to_string());
+            }
+        }
+        if let Some((header, children)) = current.take() {
+            config.push(header, children);
+        }
+        config
+    }
+
+    fn push(&mut self, header: String, children: Vec<(usize, String)>) {
+        let kind = ConfigBlockKind::of(&header);
+        if children.is_empty() && kind == ConfigBlockKind::Other {
+            self.global.push(header);
+        } else {
+            self.blocks.push(ConfigBlock {
+                kind,
+                header,
+                children: nest(&children),
+            });
+        }
+    }
+}
+
+/// Delimiter of a `banner` whose text continues on the following lines. IOS prints the usual
+/// Ctrl-C delimiter as `^C`.
+fn open_banner(line: &str) -> Option<String> {
+    let mut words = line.splitn(3, ' ');
+    if words.next() != Some("banner") {
+        return None;
+    }
+    let text = words.nth(1)?;
+    let delimiter = match text.strip_prefix("^C") {
+        Some(_) => "^C".to_string(),
+        None => text.chars().next()?.to_string(),
+    };
+    (!text[delimiter.len()..].contains(delimiter.as_str())).then_some(delimiter)
+}
+
+/// Builds the child tree from lines in config order using their indentation.
+fn nest(lines: &[(usize, String)]) -> Vec<ConfigLine> {
+    let mut nodes = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let (indent, line) = &lines[index];
+        let end = lines[index + 1..].iter()
+            .position(|(child_indent, _)| child_indent <= indent)
+            .map_or(lines.len(), |offset| index + 1 + offset);
+        nodes.push(ConfigLine {
+            line: line.clone(),
+            children: nest(&lines[index + 1..end]),
+        });
+        index = end;
+    }
+    nodes
+}
+
+impl ConfigBlockKind {
+    pub fn of(header: &str) -> Self {
+        match header.split_whitespace().next().unwrap_or_default() {
+            "interface" => ConfigBlockKind::Interface,
+            "vlan" => ConfigBlockKind::Vlan,
+            "router" => ConfigBlockKind::Router,
+            "line" => ConfigBlockKind::Line,
+            "banner" => ConfigBlockKind::Banner,
+            _ => ConfigBlockKind::Other,
+        }
+    }
+}
+
+impl ConfigBlock {
+    /// Header followed by the children indented one space per level, as IOS prints them.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![self.header.clone()];
+        flatten(&self.children, 1, &mut lines);
+        lines
+    }
+}
+
+fn flatten(children: &[ConfigLine], depth: usize, lines: &mut Vec<String>) {
+    for child in children {
+        lines.push(format!("{}{}", " ".repeat(depth), child.line));
+        flatten(&child.children, depth + 1, lines);
+    }
+}
+
+/// Unified diff of two configs done section by section, so a change inside an interface is
+/// reported under that interface even when the blocks moved around. Sections are ordered as in
+/// `to`, followed by the ones only present in `from`.
+pub fn section_diff(from: &ParsedConfig, from_name: &str, to: &ParsedConfig, to_name: &str) -> String {
+    let mut sections: Vec<(String, Vec<String>, Vec<String>)> = vec![
+        ("global".to_string(), from.global.clone(), to.global.clone())
+    ];
+    for block in &to.blocks {
+        let old = from.blocks.iter()
+            .find(|old| old.header == block.header)
+            .map(|old| old.lines())
+            .unwrap_or_default();
+        sections.push((block.header.clone(), old, block.lines()));
+    }
+    for block in &from.blocks {
+        if !to.blocks.iter().any(|new| new.header == block.header) {
+            sections.push((block.header.clone(), block.lines(), Vec::new()));
+        }
+    }
+
+    let mut diff = String::new();
+    for (header, old, new) in sections {
+        if old == new {
+            continue;
+        }
+        let old: Vec<&str> = old.iter().map(|line| line.as_str()).collect();
+        let new: Vec<&str> = new.iter().map(|line| line.as_str()).collect();
+        let text_diff = TextDiff::from_slices(&old, &new);
+        for group in text_diff.grouped_ops(3) {
+            diff.push_str(&format!("@@ {} @@\n", header));
+            for op in group {
+                for change in text_diff.iter_changes(&op) {
+                    let sign = match change.tag() {
+                        ChangeTag::Delete => '-',
+                        ChangeTag::Insert => '+',
+                        ChangeTag::Equal => ' ',
+                    };
+                    diff.push_str(&format!("{}{}\n", sign, change.value()));
+                }
+            }
+        }
+    }
+
+    if diff.is_empty() {
+        return diff;
+    }
+    format!("--- {}\n+++ {}\n{}", from_name, to_name, diff)
+}
+
+/// Lines of a config as they have to be typed in config mode, without comments and the preamble.
+pub fn config_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('!') && *line != "end")
+        .filter(|line| !PREAMBLE.iter().any(|p| line.starts_with(p)))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Lines of a config as `configure replace` reads them from a file, with their indentation and
+/// ending in the `end` it stops at.
+pub fn replace_lines(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = text.lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !PREAMBLE.iter().any(|p| line.starts_with(p)))
+        .map(|line| line.to_string())
+        .collect();
+    let end = lines.iter().position(|line| line == "end").unwrap_or(lines.len());
+    lines.truncate(end);
+    while lines.first().is_some_and(|line| line.is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines.push("end".to_string());
+    lines
+}
+
+/// Hostnames IOS accepts are at most this long.
+const MAX_HOSTNAME_LEN: usize = 63;
+const MAX_VLAN_NAME_LEN: usize = 32;
+
+/// Checks a value that ends up as a single word of a CLI line. A line break or control
+/// character in it would end the line and run the rest as a command of its own.
+pub fn cli_word<'a>(field: &str, value: &'a str, max_len: usize) -> Result<&'a str, ExecutionError> {
+    if value.is_empty() || value.chars().count() > max_len {
+        return Err(ExecutionError { message: format!("{} has to be 1 to {} characters long.", field, max_len) });
+    }
+    if value.chars().any(|c| c.is_control() || c.is_whitespace()) {
+        return Err(ExecutionError { message: format!("{} can't contain whitespace or control characters.", field) });
+    }
+    Ok(value)
+}
+
+pub fn check_hostname(hostname: &str) -> Result<&str, ExecutionError> {
+    cli_word("Hostname", hostname, MAX_HOSTNAME_LEN)
+}
+
+pub fn check_vlan_name(name: &str) -> Result<&str, ExecutionError> {
+    cli_word("VLAN name", name, MAX_VLAN_NAME_LEN)
+}
+
+/// Hostname set by the `hostname` line of a config.
+pub fn config_hostname(config: &str) -> Option<String> {
+    config.lines()
+        .find_map(|line| line.strip_prefix("hostname "))
+        .map(|hostname| hostname.trim().to_string())
+}
+
+/// Error messages IOS printed in `output`, e.g. `% Invalid input detected at '^' marker.`.
+/// Syslog messages like `%LINK-3-UPDOWN: ...` also start with % but aren't errors.
+pub fn ios_errors(output: &str) -> Vec<String> {
+    output.lines()
+        .map(|line| line.trim())
+        .filter(|line| line.starts_with('%') && !is_syslog(line))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn is_syslog(line: &str) -> bool {
+    let tag = line.trim_start_matches('%').split(':').next().unwrap_or_default();
+    let parts: Vec<&str> = tag.split('-').collect();
+    parts.len() >= 3
+        && parts.iter().all(|part| !part.is_empty() && !part.contains(' '))
+        && parts[1].chars().all(|c| c.is_ascii_digit())
+}
+
+/// Drops the echoed command and the trailing prompt from console output.
+pub fn strip_echo_and_prompt(output: &str) -> String {
+    let mut lines: Vec<&str> = output.lines().skip(1).collect();
+    while let Some(last) = lines.last() {
+        let last = last.trim();
+        if last.is_empty() || (!last.contains(' ') && (last.ends_with('#') || last.ends_with('>'))) {
+            lines.pop();
+        } else {
+            break;
+        }
+    }
+    lines.join("\n")
+}
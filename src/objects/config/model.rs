@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// IOS configuration split into top level lines and the blocks opened by indented children.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ParsedConfig {
+    pub global: Vec<String>,
+    pub blocks: Vec<ConfigBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ConfigBlock {
+    pub kind: ConfigBlockKind,
+    pub header: String,
+    pub children: Vec<ConfigLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ConfigLine {
+    pub line: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    #[schema(no_recursion)]
+    pub children: Vec<ConfigLine>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigBlockKind {
+    Interface,
+    Vlan,
+    Router,
+    Line,
+    Banner,
+    Other,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RunningConfigDTO {
+    pub text: String,
+    pub config: ParsedConfig,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConfigDiffDTO {
+    pub unsaved_changes: bool,
+    pub diff: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ConfigQuery {
+    /// `text` returns the config verbatim as text/plain instead of JSON.
+    pub format: Option<String>,
+    /// Reads the config from the device first instead of returning the stored one.
+    pub refresh: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RestoreMode {
+    /// Applies the lines on top of the running config.
+    #[default]
+    Merge,
+    /// Makes the running config exactly the given one with `configure replace`.
+    Replace,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RestoreDTO {
+    /// Archived version to restore, see `GET /api/v1/devices/{id}/backups`.
+    pub version: Option<String>,
+    /// Config text to restore instead of an archived version.
+    pub config: Option<String>,
+    #[serde(default)]
+    pub mode: RestoreMode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LineError {
+    /// 1 based position in the list of lines sent to the device, missing when IOS didn't say
+    /// which line it failed on.
+    pub line_number: Option<usize>,
+    pub line: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RestoreReport {
+    pub mode: RestoreMode,
+    pub lines_sent: usize,
+    pub errors: Vec<LineError>,
+}
@@ -0,0 +1,225 @@
+use std::fmt::{Debug, Formatter};
+use std::io::ErrorKind;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::mpsc::{self, TryRecvError};
+use std::time::{Duration, Instant};
+use serial2::SerialPort;
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::model::*;
+use crate::handlers::audit_handler::model::AuditSession;
+use crate::handlers::recording_handler::model::RecordingSession;
+
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+/// How long `bridge` waits for output before checking for new input again.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Output an audit entry of an interactive session holds at most, longer output is split.
+const MAX_ENTRY_BYTES: usize = 64 * 1024;
+
+impl SerialConsole {
+    pub fn new(path: &str) -> Self {
+        SerialConsole {
+            path: path.to_string(),
+            port: None,
+        }
+    }
+
+    fn port(&mut self) -> std::io::Result<&mut SerialPort> {
+        if self.port.is_none() {
+            let mut port = SerialPort::open(&self.path, BAUD_RATE)?;
+            port.set_read_timeout(READ_TIMEOUT)?;
+            self.port = Some(port);
+        }
+        Ok(self.port.as_mut().expect("Port was opened above."))
+    }
+}
+
+impl Debug for SerialConsole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SerialConsole")
+            .field("path", &self.path)
+            .field("open", &self.port.is_some())
+            .finish()
+    }
+}
+
+impl Console for SerialConsole {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        let result = self.port()?.write_all(data);
+        if result.is_err() {
+            // reopen on next use, the adapter might have been replugged
+            self.port = None;
+        }
+        result
+    }
+
+    fn read_available(&mut self) -> std::io::Result<Vec<u8>> {
+        let port = self.port()?;
+        let mut response = Vec::new();
+        let mut buffer = [0u8; 1024];
+        loop {
+            match port.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => response.extend_from_slice(&buffer[..read]),
+                Err(why) if why.kind() == ErrorKind::TimedOut || why.kind() == ErrorKind::WouldBlock => break,
+                Err(why) => {
+                    self.port = None;
+                    return Err(why);
+                }
+            }
+        }
+        Ok(response)
+    }
+
+    fn read_ready(&mut self, timeout: Duration) -> std::io::Result<Vec<u8>> {
+        let port = self.port()?;
+        port.set_read_timeout(timeout)?;
+        let mut buffer = [0u8; 1024];
+        let result = port.read(&mut buffer);
+        port.set_read_timeout(READ_TIMEOUT)?;
+        match result {
+            Ok(read) => Ok(buffer[..read].to_vec()),
+            Err(why) if why.kind() == ErrorKind::TimedOut || why.kind() == ErrorKind::WouldBlock => Ok(Vec::new()),
+            Err(why) => {
+                self.port = None;
+                Err(why)
+            }
+        }
+    }
+}
+
+impl Console for DisconnectedConsole {
+    fn write(&mut self, _data: &[u8]) -> std::io::Result<()> {
+        Err(std::io::Error::new(ErrorKind::NotConnected, "Device has no console attached."))
+    }
+
+    fn read_available(&mut self) -> std::io::Result<Vec<u8>> {
+        Err(std::io::Error::new(ErrorKind::NotConnected, "Device has no console attached."))
+    }
+}
+
+impl ConsoleHandle {
+    pub fn new(console: impl Console + 'static) -> Self {
+        ConsoleHandle(Arc::new(Mutex::new(Box::new(console))))
+    }
+
+    pub fn serial(path: &str) -> Self {
+        ConsoleHandle::new(SerialConsole::new(path))
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, Box<dyn Console>> {
+        // a panic mid exchange leaves the line in an unknown state, but the next command resyncs it
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Sends `command` and returns everything the device printed in response.
+    pub fn execute(&self, command: &str) -> std::io::Result<String> {
+        let mut console = self.lock();
+        console.write(command.as_bytes())?;
+        let response = console.read_available()?;
+        Ok(String::from_utf8_lossy(&response).to_string())
+    }
+
+    /// Writes what arrives on `input` to the console and passes what it prints to `output`,
+    /// until either side goes away or nothing is typed for `idle_timeout`. Holds the console
+    /// lock the whole time, so nothing else can talk to the device meanwhile. Everything typed
+    /// and printed is passed to `transcript`.
+    pub fn bridge(&self, input: &mpsc::Receiver<Vec<u8>>, output: &UnboundedSender<Vec<u8>>, transcript: &mut Transcript, idle_timeout: Duration) -> std::io::Result<()> {
+        let mut console = self.lock();
+        let mut last_input = Instant::now();
+        loop {
+            loop {
+                match input.try_recv() {
+                    Ok(data) => {
+                        console.write(&data)?;
+                        transcript.push_typed(&data);
+                        last_input = Instant::now();
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            }
+            if last_input.elapsed() >= idle_timeout {
+                let notice = format!("\r\n% Console session closed after {}s without input.\r\n", idle_timeout.as_secs());
+                let _ = output.send(notice.into_bytes());
+                return Ok(());
+            }
+            let printed = console.read_ready(POLL_INTERVAL)?;
+            if printed.is_empty() {
+                continue;
+            }
+            transcript.push_printed(&printed);
+            if output.send(printed).is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Transcript {
+    pub fn new(audit: Option<AuditSession>, hostname: &str, recording: Option<RecordingSession>) -> Self {
+        Transcript {
+            audit,
+            hostname: hostname.to_string(),
+            recording,
+            typed: Vec::new(),
+            printed: Vec::new(),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn push_typed(&mut self, data: &[u8]) {
+        if let Some(recording) = &self.recording {
+            recording.input(data);
+        }
+        // a finished line is audited once the next one starts, its output has arrived by then
+        let ends_line = |data: &[u8]| data.iter().any(|byte| matches!(byte, b'\r' | b'\n'));
+        let starts_line = data.iter().any(|byte| !matches!(byte, b'\r' | b'\n' | 0));
+        if starts_line && ends_line(&self.typed) {
+            self.flush(None);
+        }
+        self.typed.extend_from_slice(data);
+        if self.typed.len() > MAX_ENTRY_BYTES {
+            self.flush(None);
+        }
+    }
+
+    pub fn push_printed(&mut self, data: &[u8]) {
+        if let Some(recording) = &self.recording {
+            recording.output(data);
+        }
+        self.printed.extend_from_slice(data);
+        if self.printed.len() > MAX_ENTRY_BYTES {
+            self.flush(None);
+        }
+    }
+
+    /// Audits what's left once the session ends, with the error it ended on if any.
+    pub fn finish(&mut self, result: &std::io::Result<()>) {
+        self.flush(result.as_ref().err());
+    }
+
+    fn flush(&mut self, error: Option<&std::io::Error>) {
+        if self.typed.is_empty() && self.printed.is_empty() && error.is_none() {
+            return;
+        }
+        let typed = std::mem::take(&mut self.typed);
+        let printed = std::mem::take(&mut self.printed);
+        let started = std::mem::replace(&mut self.started, Instant::now());
+        let Some(audit) = &self.audit else {
+            return;
+        };
+        let command = String::from_utf8_lossy(&typed).replace("\r\n", "\n").replace('\r', "\n");
+        let printed = match error {
+            None => Ok(String::from_utf8_lossy(&printed).to_string()),
+            Some(why) => Err(std::io::Error::new(why.kind(), why.to_string())),
+        };
+        audit.record(&self.hostname, &command, &printed, started.elapsed());
+    }
+}
+
+impl Default for ConsoleHandle {
+    fn default() -> Self {
+        ConsoleHandle::new(DisconnectedConsole)
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use serial2::SerialPort;
+
+use crate::handlers::audit_handler::model::AuditSession;
+use crate::handlers::recording_handler::model::RecordingSession;
+
+/// Line speed of the console ports, the default of Cisco consoles.
+pub const BAUD_RATE: u32 = 9600;
+
+/// Byte level access to a device's console line.
+pub trait Console: Send + Debug {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<()>;
+    /// Reads whatever the device prints until it stays quiet for the console's read timeout.
+    fn read_available(&mut self) -> std::io::Result<Vec<u8>>;
+    /// Reads what the device printed so far, waiting at most `timeout` for the first byte.
+    /// Interactive sessions use it to pass output on as it comes.
+    fn read_ready(&mut self, _timeout: std::time::Duration) -> std::io::Result<Vec<u8>> {
+        self.read_available()
+    }
+}
+
+/// Console attached to a serial port, opened on first use and kept open afterwards.
+pub struct SerialConsole {
+    pub(crate) path: String,
+    pub(crate) port: Option<SerialPort>,
+}
+
+/// Used for devices that aren't attached to anything, every operation fails.
+#[derive(Debug)]
+pub struct DisconnectedConsole;
+
+/// Shared handle to a device console, the lock makes sure exchanges on the line don't interleave.
+#[derive(Debug, Clone)]
+pub struct ConsoleHandle(pub(crate) Arc<Mutex<Box<dyn Console>>>);
+
+/// Hands what passes through `ConsoleHandle::bridge` on as it passes. The audit log gets an
+/// entry per typed line with the output that followed it, so only one exchange is ever held.
+#[derive(Debug)]
+pub struct Transcript {
+    pub(crate) audit: Option<AuditSession>,
+    pub(crate) hostname: String,
+    pub(crate) recording: Option<RecordingSession>,
+    /// Typed and printed since the last audit entry.
+    pub(crate) typed: Vec<u8>,
+    pub(crate) printed: Vec<u8>,
+    pub(crate) started: Instant,
+}
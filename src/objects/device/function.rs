@@ -1,13 +1,29 @@
-use super::model::NetworkDevice;
+use super::model::{ChangeFailure, ChangesReport, DeviceChange, NetworkDevice};
 use crate::objects::interface::model::*;
 use crate::objects::vlan::model::*;
+use crate::objects::config::function::{check_hostname, check_vlan_name, config_hostname, config_lines, ios_errors, replace_lines, strip_echo_and_prompt};
+use crate::objects::config::model::{LineError, ParsedConfig, RestoreMode, RestoreReport};
+use crate::objects::console::model::Transcript;
 
 use std::collections::HashMap;
-use std::io::Read;
-use serial2::SerialPort;
+use std::net::Ipv4Addr;
+use std::sync::mpsc;
+use std::time::{self, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+use chrono::{Duration, Utc};
+use log::debug;
 use substring::Substring;
 use crate::errors::execution_error::ExecutionError;
 
+/// Where `configure replace` reads the config to restore from.
+const RESTORE_FILE: &str = "flash:rpi-restore.cfg";
+/// Snapshot of the running config that failed changes are rolled back to.
+const ROLLBACK_FILE: &str = "flash:rpi-rollback.cfg";
+/// Sent before commands whose output has to be read in full.
+const PREPARE_EXEC: [&str; 2] = ["en", "terminal length 0"];
+/// Ctrl-Z, leaves whatever config mode an interactive session ended in.
+const END_CONFIG: &str = "\u{1a}";
+
 impl Default for NetworkDevice {
     fn default() -> Self {
         NetworkDevice {
@@ -19,56 +35,386 @@ impl Default for NetworkDevice {
             interfaces: HashMap::new(),
             startup_config: "".to_string(),
             running_config: "".to_string(),
+            unsaved_changes: false,
+            confirm_deadline: None,
+            console: Default::default(),
+            audit: None,
+            recording: None,
         }
     }
 }
 
 impl NetworkDevice {
     pub fn execute_command(&mut self, command:&str) -> Result<String, std::io::Error> {
-        let mut response = &mut "".to_string();
-        match SerialPort::open(self.s_port.clone(), 9600) {
-            Ok(mut port) => {
-                match port.write(command.as_ref()) {
-                    Ok(_res) => {
-                        port.read_to_string(response);
-                        println!("{}",response);
-                        return Ok(response.clone());
-                    }
-                    Err(why) => return Err(why)
-                }
+        let mut command = command.to_string();
+        if !command.ends_with('\n') {
+            command.push('\n');
+        }
+        let started = Instant::now();
+        if let Some(recording) = &self.recording {
+            recording.input(command.as_bytes());
+        }
+        let result = self.console.execute(&command);
+        if let (Some(recording), Ok(response)) = (&self.recording, &result) {
+            recording.output(response.as_bytes());
+        }
+        if let Some(audit) = &self.audit {
+            audit.record(&self.hostname, &command, &result, started.elapsed());
+        }
+        let response = result?;
+        debug!("{}: {}", self.hostname, response);
+        Ok(response)
+    }
+
+    /// Hands the console to an interactive session until `input` is closed or nothing is typed
+    /// for `idle_timeout`, see `ConsoleHandle::bridge`. Every typed line ends up in the audit log
+    /// and in the recording as it happens. Afterwards the device is taken back to privileged
+    /// mode and reread, since the session may have changed anything.
+    pub fn console_session(&mut self, input: &mpsc::Receiver<Vec<u8>>, output: &UnboundedSender<Vec<u8>>, idle_timeout: time::Duration) -> Result<(), ExecutionError> {
+        let mut transcript = Transcript::new(self.audit.clone(), &self.hostname, self.recording.clone());
+        let result = self.console.bridge(input, output, &mut transcript, idle_timeout);
+        transcript.finish(&result);
+        result.map_err(console_error)?;
+        self.execute_command(END_CONFIG).map_err(console_error)?;
+        self.prepare_exec()?;
+        self.read_state()
+    }
+
+    /// Lines `change_hostname` sends, in order.
+    pub fn hostname_commands(&self, hostname: &str) -> Result<Vec<String>, ExecutionError> {
+        let hostname = check_hostname(hostname)?;
+        Ok(vec!["en".to_string(), "conf t".to_string(), format!("hostname {}", hostname), "end".to_string()])
+    }
+
+    pub fn change_hostname(&mut self, hostname: &str) -> Result<&NetworkDevice, ExecutionError> {
+        match self.execute_command(&self.hostname_commands(hostname)?.join("\n")) {
+            Ok(_response) => {
+                self.hostname = hostname.to_string();
+                Ok(self)
+            }
+            Err(_why) => { //This error should probably be piped to some kind of per device error handling for case when it stops working mid session
+                Err(ExecutionError { message: "Couldn't change hostname".to_string()})
             }
-            Err(why) => return Err(why)
         }
     }
 
+    /// Enters privileged mode and disables paging so long outputs come back in one piece.
+    fn prepare_exec(&mut self) -> Result<(), ExecutionError> {
+        self.execute_command(&PREPARE_EXEC.join("\n"))
+            .map(|_| ())
+            .map_err(|why| ExecutionError { message: why.to_string() })
+    }
+
     pub fn read_running_config(&mut self) -> Result<String, ExecutionError> {
-        return match self.execute_command("sh running-config") {
+        self.prepare_exec()?;
+        match self.execute_command("sh running-config") {
             Err(why) => {
                 Err(ExecutionError {
                     message: why.to_string()
                 })
             }
             Ok(result) => {
-                self.running_config = result.lines().skip(1).collect();
+                self.running_config = strip_echo_and_prompt(&result);
+                self.update_unsaved_changes();
                 Ok("Successfully read running-config".to_string())
             }
         }
     }
 
     pub fn read_startup_config(&mut self) -> Result<String, ExecutionError> {
-        return match self.execute_command("sh startup-config") {
+        self.prepare_exec()?;
+        match self.execute_command("sh startup-config") {
             Err(why) => {
                 Err(ExecutionError {
                     message: why.to_string()
                 })
             }
             Ok(result) => {
-                self.startup_config = result.lines().skip(1).collect();
+                self.startup_config = strip_echo_and_prompt(&result);
+                self.update_unsaved_changes();
                 Ok("Successfully read startup-config".to_string())
             }
         }
     }
 
+    /// Lines `save_config` sends, the empty one accepts the destination filename prompt.
+    pub fn save_commands(&self) -> Vec<String> {
+        let mut commands = PREPARE_EXEC.map(String::from).to_vec();
+        commands.push("copy running-config startup-config".to_string());
+        commands.push("".to_string());
+        commands
+    }
+
+    /// Copies the running config to NVRAM so it survives a reload, then rereads both configs since
+    /// the running one may have changed since the agent last read it.
+    pub fn save_config(&mut self) -> Result<&NetworkDevice, ExecutionError> {
+        self.prepare_exec()?;
+        let mut output = self.execute_command("copy running-config startup-config")
+            .map_err(|why| ExecutionError { message: format!("Couldn't save config because: {}", why) })?;
+        if output.contains("Destination filename") {
+            // accept the default [startup-config]
+            output += &self.execute_command("\n")
+                .map_err(|why| ExecutionError { message: format!("Couldn't save config because: {}", why) })?;
+        }
+        if !output.contains("[OK]") {
+            return Err(ExecutionError { message: format!("Device didn't confirm saving config: {}", output.trim()) });
+        }
+        self.read_running_config()?;
+        self.read_startup_config()?;
+        Ok(self)
+    }
+
+    /// Applies `config` through the console and rereads the running config afterwards.
+    pub fn restore_config(&mut self, config: &str, mode: RestoreMode) -> Result<RestoreReport, ExecutionError> {
+        let lines = restore_lines(config, mode)?;
+        self.prepare_exec()?;
+        let errors = match mode {
+            RestoreMode::Merge => self.merge_config(&lines)?,
+            RestoreMode::Replace => self.replace_config(&lines)?,
+        };
+        self.read_running_config()?;
+
+        Ok(RestoreReport {
+            mode,
+            lines_sent: lines.len(),
+            errors,
+        })
+    }
+
+    /// Lines `restore_config` sends, in order.
+    pub fn restore_commands(&self, config: &str, mode: RestoreMode) -> Result<Vec<String>, ExecutionError> {
+        let lines = restore_lines(config, mode)?;
+        let mut commands = PREPARE_EXEC.map(String::from).to_vec();
+        match mode {
+            RestoreMode::Merge => {
+                commands.push("conf t".to_string());
+                commands.extend(lines);
+                commands.push("end".to_string());
+            }
+            RestoreMode::Replace => {
+                commands.extend(write_restore_file(&lines));
+                commands.push(format!("configure replace {} force", RESTORE_FILE));
+            }
+        }
+        Ok(commands)
+    }
+
+    /// Types the lines one by one in config mode so every IOS error can be tied to its line.
+    fn merge_config(&mut self, lines: &[String]) -> Result<Vec<LineError>, ExecutionError> {
+        self.execute_command("conf t").map_err(console_error)?;
+        let mut errors = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            let output = self.execute_command(line).map_err(console_error)?;
+            errors.extend(ios_errors(&output).into_iter().map(|error| LineError {
+                line_number: Some(index + 1),
+                line: line.clone(),
+                error,
+            }));
+        }
+        self.execute_command("end").map_err(console_error)?;
+        Ok(errors)
+    }
+
+    /// `configure replace` needs the config in a file, so it's written to flash with tclsh first.
+    fn replace_config(&mut self, lines: &[String]) -> Result<Vec<LineError>, ExecutionError> {
+        let mut output = String::new();
+        for command in write_restore_file(lines) {
+            output += &self.execute_command(&command).map_err(console_error)?;
+        }
+        if let Some(error) = ios_errors(&output).into_iter().next() {
+            return Err(ExecutionError { message: format!("Couldn't write {}: {}", RESTORE_FILE, error) });
+        }
+
+        let output = self.execute_command(&format!("configure replace {} force", RESTORE_FILE)).map_err(console_error)?;
+        Ok(replace_errors(&output, lines))
+    }
+
+    /// Lines `change` sends, in order.
+    pub fn change_commands(&self, change: &DeviceChange) -> Result<Vec<String>, ExecutionError> {
+        match change {
+            DeviceChange::Hostname { hostname } => self.hostname_commands(hostname),
+            DeviceChange::AddVlan(vlan) => self.add_vlan_commands(vlan),
+            DeviceChange::RemoveVlan { vlan_id } => Ok(self.remove_vlan_commands(*vlan_id)),
+            DeviceChange::ConfigureInterface { interface_id, interface } => self.interface_commands(*interface_id, interface),
+        }
+    }
+
+    /// Applies `changes` as one unit. The running config is copied to flash first and the device
+    /// is rolled back to it with `configure replace` on the first line IOS rejects or the console
+    /// fails to deliver.
+    ///
+    /// With `confirm_minutes` a reload is scheduled before anything is applied, so a change that
+    /// cuts the agent off is undone by the device itself unless `confirm_changes` runs in time.
+    pub fn apply_changes(&mut self, changes: &[DeviceChange], confirm_minutes: Option<u32>) -> Result<ChangesReport, ExecutionError> {
+        match self.confirm_deadline {
+            Some(deadline) if deadline > Utc::now() => {
+                return Err(ExecutionError { message: "Previous confirmed change is still waiting for confirmation.".to_string() });
+            }
+            Some(_) => self.expire_confirmation()?,
+            None => {}
+        }
+        // fail on unknown interfaces before touching the device
+        let steps = changes.iter()
+            .map(|change| self.change_commands(change))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.prepare_exec()?;
+        if confirm_minutes.is_some() {
+            // the reload brings back the startup config, which has to be all that's worth keeping
+            self.read_running_config()?;
+            self.read_startup_config()?;
+            if self.unsaved_changes {
+                return Err(ExecutionError { message: "Running config has unsaved changes, save them before a confirmed change.".to_string() });
+            }
+        }
+        self.copy_running_config(ROLLBACK_FILE)?;
+        let hostname = self.hostname.clone();
+        if let Some(minutes) = confirm_minutes {
+            self.schedule_reload(minutes)?;
+            // from here on the reload is pending whatever fails, callers have to see it
+            self.confirm_deadline = Some(Utc::now() + Duration::minutes(minutes.into()));
+        }
+
+        for (index, commands) in steps.iter().enumerate() {
+            for line in commands {
+                let error = match self.execute_command(line) {
+                    Ok(output) => ios_errors(&output).into_iter().next(),
+                    Err(why) => Some(console_error(why).message),
+                };
+                if let Some(error) = error {
+                    let failure = ChangeFailure { step: index + 1, line: line.clone(), error };
+                    self.hostname = hostname;
+                    // the scheduled reload has to go even when the rollback fails
+                    let rollback = self.rollback();
+                    let cancel = match confirm_minutes {
+                        Some(_) => self.cancel_reload(),
+                        None => Ok(()),
+                    };
+                    if cancel.is_ok() {
+                        self.confirm_deadline = None;
+                    }
+                    let rolled_back = rollback?;
+                    cancel?;
+                    return Ok(ChangesReport { applied: index, failure: Some(failure), rolled_back, confirm_deadline: None });
+                }
+            }
+            if let DeviceChange::Hostname { hostname } = &changes[index] {
+                self.hostname = hostname.clone();
+            }
+        }
+
+        self.read_vlans();
+        self.read_interfaces()?;
+        self.read_running_config()?;
+        Ok(ChangesReport { applied: changes.len(), failure: None, rolled_back: false, confirm_deadline: self.confirm_deadline })
+    }
+
+    /// Lines `confirm_changes` sends, in order.
+    pub fn confirm_commands(&self) -> Result<Vec<String>, ExecutionError> {
+        match self.confirm_deadline {
+            None => return Err(ExecutionError { message: "No change is waiting for confirmation.".to_string() }),
+            Some(deadline) if deadline < Utc::now() => {
+                return Err(ExecutionError { message: "Confirmation comes too late, the device reloaded its startup config.".to_string() });
+            }
+            Some(_) => {}
+        }
+        let mut commands = PREPARE_EXEC.map(String::from).to_vec();
+        commands.push("reload cancel".to_string());
+        commands.extend(self.save_commands());
+        Ok(commands)
+    }
+
+    /// Keeps the changes of a confirmed change by cancelling the scheduled reload and saving.
+    pub fn confirm_changes(&mut self) -> Result<&NetworkDevice, ExecutionError> {
+        match self.confirm_deadline {
+            None => return Err(ExecutionError { message: "No change is waiting for confirmation.".to_string() }),
+            Some(deadline) if deadline < Utc::now() => {
+                self.expire_confirmation()?;
+                return Err(ExecutionError { message: "Confirmation came too late, the device reloaded its startup config.".to_string() });
+            }
+            Some(_) => {}
+        }
+        self.prepare_exec()?;
+        self.cancel_reload()?;
+        self.confirm_deadline = None;
+        self.save_config()
+    }
+
+    /// Forgets a confirmed change whose deadline passed. The device reloaded its startup config
+    /// then, so the hostname, interfaces, VLANs and configs read before are stale.
+    fn expire_confirmation(&mut self) -> Result<(), ExecutionError> {
+        self.confirm_deadline = None;
+        self.read_startup_config()?;
+        self.read_state()
+    }
+
+    /// Rereads the running config and what's derived from it after the device changed behind
+    /// the agent's back.
+    fn read_state(&mut self) -> Result<(), ExecutionError> {
+        self.read_running_config()?;
+        if let Some(hostname) = config_hostname(&self.running_config) {
+            self.hostname = hostname;
+        }
+        self.read_vlans();
+        self.read_interfaces().map(|_| ())
+    }
+
+    /// Schedules `reload in`, without saving the running config so the reload reverts it.
+    fn schedule_reload(&mut self, minutes: u32) -> Result<(), ExecutionError> {
+        let mut output = self.execute_command(&format!("reload in {}", minutes)).map_err(console_error)?;
+        if output.contains("Save?") {
+            output += &self.execute_command("no").map_err(console_error)?;
+        }
+        if output.contains("[confirm]") {
+            output += &self.execute_command("\n").map_err(console_error)?;
+        }
+        if !output.contains("Reload scheduled") {
+            return Err(ExecutionError { message: format!("Couldn't schedule reload: {}", output.trim()) });
+        }
+        Ok(())
+    }
+
+    fn cancel_reload(&mut self) -> Result<(), ExecutionError> {
+        let output = self.execute_command("reload cancel").map_err(console_error)?;
+        match ios_errors(&output).into_iter().next() {
+            Some(error) => Err(ExecutionError { message: format!("Couldn't cancel scheduled reload: {}", error) }),
+            None => Ok(()),
+        }
+    }
+
+    /// Copies the running config to `file`, answering the filename and overwrite prompts.
+    fn copy_running_config(&mut self, file: &str) -> Result<(), ExecutionError> {
+        let mut output = self.execute_command(&format!("copy running-config {}", file)).map_err(console_error)?;
+        if output.contains("Destination filename") {
+            output += &self.execute_command("\n").map_err(console_error)?;
+        }
+        if output.contains("[confirm]") {
+            output += &self.execute_command("\n").map_err(console_error)?;
+        }
+        if !output.contains("bytes copied") {
+            return Err(ExecutionError { message: format!("Couldn't copy running config to {}: {}", file, output.trim()) });
+        }
+        Ok(())
+    }
+
+    /// Reverts the running config to the snapshot in `ROLLBACK_FILE`, true when IOS accepted it.
+    /// The hostname is read back from the reverted config.
+    fn rollback(&mut self) -> Result<bool, ExecutionError> {
+        self.execute_command("end").map_err(console_error)?;
+        let output = self.execute_command(&format!("configure replace {} force", ROLLBACK_FILE)).map_err(console_error)?;
+        let rolled_back = ios_errors(&output).is_empty();
+        if !rolled_back {
+            println!("Couldn't roll back {}: {}", self.hostname, output);
+        }
+        self.read_state()?;
+        Ok(rolled_back)
+    }
+
+    fn update_unsaved_changes(&mut self) {
+        self.unsaved_changes = !self.running_config.is_empty()
+            && ParsedConfig::parse(&self.running_config) != ParsedConfig::parse(&self.startup_config);
+    }
+
     pub fn set_vlans(&mut self, vlans: HashMap<u32, Vlan>) {
         self.vlans = vlans;
     }
@@ -89,10 +435,10 @@ impl NetworkDevice {
                         name_index = line.find("Name").unwrap_or(5);
                         status_index = line.find("Status").unwrap_or(38);
                         ports_index = line.find("Ports").unwrap_or(48);
-                    } else if (line.starts_with("-")){
+                    } else if line.starts_with("-") {
 
                     } else {
-                        let nr = line.substring(0,name_index.checked_sub(1).unwrap_or(0)).trim().parse::<u32>();
+                        let nr = line.substring(0,name_index.saturating_sub(1)).trim().parse::<u32>();
                         match nr {
                             Ok(nr) => {
                                 let name = line.substring(name_index, status_index-1).trim();
@@ -110,7 +456,7 @@ impl NetworkDevice {
                                 last_vlan = nr;
                             }
                             Err(_why) => {
-                                let mut vlan = vlans.get_mut(&last_vlan).expect("Should be vlan present here.");
+                                let vlan = vlans.get_mut(&last_vlan).expect("Should be vlan present here.");
                                 let ports = line.substring(ports_index, line.len()).trim();
 
                                 let mut ports_p: Vec<u32> = parse_interfaces(self, ports);
@@ -127,8 +473,13 @@ impl NetworkDevice {
         }
     }
 
+    /// Lines `remove_vlan` sends, in order.
+    pub fn remove_vlan_commands(&self, vlan_id: u32) -> Vec<String> {
+        vec!["en".to_string(), "conf t".to_string(), format!("no vlan {}", vlan_id), "end".to_string()]
+    }
+
     pub fn remove_vlan(&mut self, vlan_id: u32) -> Result<String, ExecutionError> {
-        return match self.execute_command(&format!("en\n conf t\n no vlan {}", vlan_id)) {
+        match self.execute_command(&self.remove_vlan_commands(vlan_id).join("\n")) {
             Err(why) => {
                 Err(ExecutionError {
                     message: why.to_string()
@@ -141,20 +492,17 @@ impl NetworkDevice {
         }
     }
 
+    /// Lines `add_vlan` sends, in order.
+    pub fn add_vlan_commands(&self, vlan: &VlanDTO) -> Result<Vec<String>, ExecutionError> {
+        let name = check_vlan_name(&vlan.name)?;
+        Ok(vec!["en".to_string(), "conf t".to_string(), format!("vlan {}", vlan.number), format!("name {}", name), "end".to_string()])
+    }
+
     pub fn add_vlan(&mut self, vlan: VlanDTO) -> Result<String, ExecutionError>{
-        return match self.execute_command(&format!("en\n conf t\n vlan {}", vlan.number)) {
+        match self.execute_command(&self.add_vlan_commands(&vlan)?.join("\n")) {
             Ok(_response) => {
-                match self.execute_command(&format!("name {}", vlan.name)) {
-                    Ok(_response) => {
-                        self.read_vlans();
-                        Ok(String::from("Successfully added vlan."))
-                    }
-                    Err(why) => {
-                        Err(
-                            ExecutionError { message: why.to_string()}
-                        )
-                    }
-                }
+                self.read_vlans();
+                Ok(String::from("Successfully added vlan."))
             }
             Err(why) => {
                 Err(
@@ -234,31 +582,42 @@ impl NetworkDevice {
         }
     }
 
-    pub fn configure_interface(&mut self, interface_id: u32, interface_dto: InterfaceDTO) -> Result<&mut NetworkDevice, ExecutionError> {
+    /// Id of the interface called `name`, compared without case so `fastethernet0/1` works too.
+    pub fn interface_id(&self, name: &str) -> Result<u32, ExecutionError> {
+        self.interfaces.iter()
+            .find(|(_id, interface)| interface.name().eq_ignore_ascii_case(name))
+            .map(|(id, _interface)| *id)
+            .ok_or(ExecutionError{message: format!("Couldn't find interface {}.", name)})
+    }
+
+    /// Lines `configure_interface` sends, in order.
+    pub fn interface_commands(&self, interface_id: u32, interface_dto: &InterfaceDTO) -> Result<Vec<String>, ExecutionError> {
         let interface = self.get_interface(interface_id)?;
-        let status = match interface_dto.status.as_ref() {
-            "up" => {
-                "no shutdown"
-            },
-            "down" => {
-                "shutdown"
-            }
-            _ => {""}
-        };
+        let ip_address = parse_ipv4("IP address", &interface_dto.ip_address)?;
+        let mask = parse_ipv4("Mask", &interface_dto.mask)?;
+        let mut commands = vec![
+            "en".to_string(),
+            "conf t".to_string(),
+            format!("interface {} {}/{}", interface.int_type, interface.module, interface.number),
+            format!("ip address {} {}", ip_address, mask),
+        ];
+        match interface_dto.status.as_ref() {
+            "up" => commands.push("no shutdown".to_string()),
+            "down" => commands.push("shutdown".to_string()),
+            _ => {}
+        }
+        commands.push("end".to_string());
+        Ok(commands)
+    }
 
-        let output = self.execute_command(&format!("en \n conf t \n interface {} {}/{}\n ip address {} {} \n {}",
-                                                   interface.int_type,
-                                                   interface.module,
-                                                   interface.number,
-                                                   interface_dto.ip_address,
-                                                   interface_dto.mask,
-                                                   status));
-        match output{
+    pub fn configure_interface(&mut self, interface_id: u32, interface_dto: InterfaceDTO) -> Result<&mut NetworkDevice, ExecutionError> {
+        let commands = self.interface_commands(interface_id, &interface_dto)?;
+        match self.execute_command(&commands.join("\n")) {
             Ok(_response) => {
                 Ok(self.read_interfaces()?)
             },
             Err(why) => {
-                Err(ExecutionError{message: format!("{}{}","Couldn't configure interface because: ", why.to_string() )})
+                Err(ExecutionError{message: format!("{}{}","Couldn't configure interface because: ", why)})
             }
 
         }
@@ -269,6 +628,84 @@ impl NetworkDevice {
     // }
 }
 
+fn parse_ipv4(field: &str, value: &str) -> Result<Ipv4Addr, ExecutionError> {
+    value.parse().map_err(|_| ExecutionError { message: format!("{} {:?} is not an IPv4 address.", field, value) })
+}
+
+/// Config lines to restore, refusing what `mode` can't apply.
+fn restore_lines(config: &str, mode: RestoreMode) -> Result<Vec<String>, ExecutionError> {
+    if config_lines(config).is_empty() {
+        return Err(ExecutionError { message: "Config to restore is empty.".to_string() });
+    }
+    match mode {
+        RestoreMode::Merge => Ok(config_lines(config)),
+        RestoreMode::Replace => {
+            let lines = replace_lines(config);
+            check_tcl_braces(&lines)?;
+            Ok(lines)
+        }
+    }
+}
+
+/// The replace config travels as one braced Tcl word, which only stays literal while its braces
+/// are balanced and none of them or the line ends are escaped with a backslash.
+fn check_tcl_braces(lines: &[String]) -> Result<(), ExecutionError> {
+    let mut depth: usize = 0;
+    for (index, line) in lines.iter().enumerate() {
+        let refuse = |reason: &str| ExecutionError { message: format!("Line {} can't be written with tclsh, {}: {}", index + 1, reason, line) };
+        if line.ends_with('\\') || line.contains("\\{") || line.contains("\\}") {
+            return Err(refuse("it escapes a brace or the line end"));
+        }
+        for character in line.chars() {
+            match character {
+                '{' => depth += 1,
+                '}' => depth = depth.checked_sub(1).ok_or_else(|| refuse("it closes a brace that wasn't opened"))?,
+                _ => {}
+            }
+        }
+    }
+    if depth > 0 {
+        return Err(ExecutionError { message: "Config can't be written with tclsh, it leaves braces open.".to_string() });
+    }
+    Ok(())
+}
+
+/// tclsh lines that write `lines` to `RESTORE_FILE` and close it again.
+fn write_restore_file(lines: &[String]) -> Vec<String> {
+    let mut commands = vec![
+        "tclsh".to_string(),
+        format!("set f [open \"{}\" w+]", RESTORE_FILE),
+        "puts $f {".to_string(),
+    ];
+    commands.extend(lines.iter().cloned());
+    commands.push("}".to_string());
+    commands.push("close $f".to_string());
+    commands.push("tclquit".to_string());
+    commands
+}
+
+/// Errors `configure replace` printed, tied to the config line IOS echoed right before each.
+fn replace_errors(output: &str, lines: &[String]) -> Vec<LineError> {
+    let output: Vec<&str> = output.lines().map(|line| line.trim()).collect();
+    output.iter().enumerate()
+        .filter_map(|(index, line)| Some((index, ios_errors(line).into_iter().next()?)))
+        .map(|(index, error)| {
+            let failed = output[..index].iter().rev()
+                .find(|line| !line.is_empty() && !line.starts_with('%') && line.chars().any(|c| c != '^' && c != ' '))
+                .and_then(|failed| lines.iter().position(|line| line.trim() == *failed));
+            LineError {
+                line_number: failed.map(|index| index + 1),
+                line: failed.map_or(format!("configure replace {}", RESTORE_FILE), |index| lines[index].clone()),
+                error,
+            }
+        })
+        .collect()
+}
+
+fn console_error(why: std::io::Error) -> ExecutionError {
+    ExecutionError { message: format!("Console exchange failed: {}", why) }
+}
+
 fn parse_interfaces(device: &NetworkDevice, ports: &str) -> Vec<u32> {
     if ports.is_empty() {
         return Vec::new();
@@ -287,12 +724,12 @@ fn parse_interfaces(device: &NetworkDevice, ports: &str) -> Vec<u32> {
         println!("Interfaces: {:?}", device.interfaces);
 
         device.interfaces.iter()
-            .filter(|(id, int)|{
+            .filter(|(_id, int)|{
                 int.int_type == kind_m && int.module == port_mod_p.parse::<u32>().unwrap() && int.number == port_nr.parse::<u32>().unwrap()
             })
-            .map(|(id, int)|{
+            .map(|(id, _int)|{
                 println!("ID: {}", id);
-                id.clone()
+                *id
             })
             .last()
             .unwrap()
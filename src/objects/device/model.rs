@@ -1,9 +1,14 @@
 use std::collections::HashMap;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use crate::objects::interface::model::Interface;
-use crate::objects::vlan::model::Vlan;
+use utoipa::ToSchema;
+use crate::handlers::audit_handler::model::AuditSession;
+use crate::handlers::recording_handler::model::RecordingSession;
+use crate::objects::console::model::ConsoleHandle;
+use crate::objects::interface::model::{Interface, InterfaceDTO};
+use crate::objects::vlan::model::{Vlan, VlanDTO};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct NetworkDevice {
     pub serial_number: String,
     pub ip_address: String,
@@ -13,4 +18,66 @@ pub struct NetworkDevice {
     pub interfaces: HashMap<u32, Interface>,
     pub startup_config: String,
     pub running_config: String,
-}
\ No newline at end of file
+    /// Running config differs from the startup config, so it would be lost on reload.
+    #[serde(default)]
+    pub unsaved_changes: bool,
+    /// A confirmed change is waiting for `POST /api/v1/devices/{id}/changes/confirm`, the device reloads
+    /// back to its startup config at this time otherwise.
+    #[serde(default)]
+    pub confirm_deadline: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub console: ConsoleHandle,
+    /// Set while the device runs a job for an API call, see `execute_command`.
+    #[serde(skip)]
+    pub audit: Option<AuditSession>,
+    /// Set next to `audit`, records the console traffic of the job.
+    #[serde(skip)]
+    pub recording: Option<RecordingSession>,
+}
+
+/// Body of `PATCH /api/v1/devices/{id}`, fields left out stay as they are.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DevicePatchDTO {
+    pub hostname: Option<String>,
+}
+
+/// One step of `POST /api/v1/devices/{id}/changes`, tagged by `op`.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum DeviceChange {
+    Hostname { hostname: String },
+    AddVlan(VlanDTO),
+    RemoveVlan { vlan_id: u32 },
+    ConfigureInterface {
+        interface_id: u32,
+        #[serde(flatten)]
+        interface: InterfaceDTO,
+    },
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ChangesDTO {
+    pub changes: Vec<DeviceChange>,
+    /// Schedules `reload in` this many minutes before applying, so the changes are only kept
+    /// when confirmed in time.
+    pub confirm_minutes: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ChangeFailure {
+    /// 1 based position of the change in the request.
+    pub step: usize,
+    pub line: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ChangesReport {
+    /// Number of changes applied, all of them unless `failure` is set.
+    pub applied: usize,
+    pub failure: Option<ChangeFailure>,
+    /// Running config was reverted to the snapshot taken before the first change.
+    pub rolled_back: bool,
+    /// Deadline for confirming the changes, set for confirmed changes that were applied.
+    pub confirm_deadline: Option<DateTime<Utc>>,
+}
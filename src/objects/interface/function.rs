@@ -0,0 +1,8 @@
+use super::model::Interface;
+
+impl Interface {
+    /// Name IOS uses for the interface, e.g. `FastEthernet0/1`.
+    pub fn name(&self) -> String {
+        format!("{}{}/{}", self.int_type, self.module, self.number)
+    }
+}
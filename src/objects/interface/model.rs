@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Interface {
     pub int_type: String,
     pub module: u32,
@@ -9,13 +10,14 @@ pub struct Interface {
     pub status: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct InterfaceDTO {
     pub ip_address: String,
     pub mask: String,
     pub status: String,
 }
 
+#[allow(dead_code, clippy::upper_case_acronyms)]
 #[derive(Debug, Deserialize)]
 pub enum InterfaceStatus {
     UP,
@@ -1,3 +1,5 @@
 pub mod device;
 pub mod vlan;
-pub mod interface;
\ No newline at end of file
+pub mod interface;
+pub mod console;
+pub mod config;
\ No newline at end of file
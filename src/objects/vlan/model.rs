@@ -1,13 +1,15 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug,Deserialize)]
+#[derive(Debug,Deserialize,ToSchema)]
 pub struct VlanDTO {
     pub number: u32,
     pub name: String,
+    #[allow(dead_code)] //TODO assign the listed interfaces to the vlan in add_vlan
     pub interfaces: Vec<u32>
 }
 
-#[derive(Debug,Clone,Serialize,Deserialize)]
+#[derive(Debug,Clone,Serialize,Deserialize,ToSchema)]
 pub struct Vlan {
     pub(crate) name: String,
     pub(crate) status: String, //TODO change this to enum with possible status values
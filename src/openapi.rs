@@ -0,0 +1,64 @@
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::handlers::{audit_handler, backup_handler, config_handler, console_handler, terminal_handler, network_devices_handler, recording_handler, template_handler};
+use crate::handlers::config_handler::model::ConfigHandler;
+use crate::objects::device::model::NetworkDevice;
+use crate::objects::interface::model::InterfaceDTO;
+use crate::objects::vlan::model::VlanDTO;
+
+/// OpenAPI document of the JSON API, served at `/openapi.json`. The HTML dashboard and the
+/// deprecated unversioned routes aren't part of it.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "rpi_client", description = "Agent managing the network devices on its serial ports."),
+    paths(crate::health),
+    nest((path = "/api/v1", api = ApiV1)),
+    modifiers(&BearerAuth),
+    security(("bearer" = [])),
+)]
+pub struct ApiDoc;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        network_devices_handler::endpoints::get_network_devices,
+        network_devices_handler::endpoints::get_network_device,
+        network_devices_handler::endpoints::update_device,
+        network_devices_handler::endpoints::list_vlans,
+        network_devices_handler::endpoints::list_interfaces,
+        network_devices_handler::endpoints::add_vlan,
+        network_devices_handler::endpoints::delete_vlan,
+        network_devices_handler::endpoints::conf_interface,
+        network_devices_handler::endpoints::apply_changes,
+        network_devices_handler::endpoints::confirm_changes,
+        network_devices_handler::endpoints::reload_configs,
+        network_devices_handler::endpoints::running_config,
+        network_devices_handler::endpoints::config_diff,
+        network_devices_handler::endpoints::save_config,
+        network_devices_handler::endpoints::restore_config,
+        config_handler::endpoints::system,
+        backup_handler::endpoints::list_backups,
+        backup_handler::endpoints::diff_backups,
+        backup_handler::endpoints::get_backup,
+        template_handler::endpoints::list_templates,
+        template_handler::endpoints::render_template,
+        template_handler::endpoints::apply_template,
+        audit_handler::endpoints::get_audit,
+        console_handler::endpoints::console,
+        terminal_handler::endpoints::list_terminals,
+        recording_handler::endpoints::list_recordings,
+        recording_handler::endpoints::get_recording,
+    ),
+    components(schemas(NetworkDevice, VlanDTO, InterfaceDTO, ConfigHandler)),
+)]
+struct ApiV1;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("bearer", SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()));
+    }
+}
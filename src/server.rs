@@ -0,0 +1,219 @@
+use std::net::SocketAddr;
+use actix_web::{App, HttpResponse, HttpServer, web, Error};
+use actix_web::http::header::LOCATION;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Server, Service, ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::middleware::{DefaultHeaders, Logger};
+use actix_web::web::Data;
+use handlebars::Handlebars;
+use log::warn;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::health;
+use crate::openapi::ApiDoc;
+use crate::tls;
+use crate::not_found;
+use crate::handlers::config_handler::model::ConfigHandler;
+use crate::handlers::config_handler::endpoints::{init_ch_endpoints, init_ch_legacy_endpoints};
+use crate::handlers::backup_handler::endpoints::{init_bh_endpoints, init_bh_legacy_endpoints};
+use crate::handlers::template_handler::endpoints::{init_th_endpoints, init_th_legacy_endpoints};
+use crate::handlers::dashboard_handler::endpoints::init_dh_endpoints;
+use crate::handlers::audit_handler::endpoints::{init_ah_endpoints, init_ah_legacy_endpoints};
+use crate::handlers::console_handler::endpoints::init_co_endpoints;
+use crate::handlers::terminal_handler::endpoints::init_te_endpoints;
+use crate::handlers::recording_handler::endpoints::init_rh_endpoints;
+use crate::handlers::terminal_handler::model::TerminalHandler;
+use crate::handlers::template_handler::model::TemplateHandler;
+use crate::handlers::auth_handler::model::AuthHandler;
+use crate::handlers::auth_handler::function::{is_dashboard, LOGIN_PATH};
+use crate::handlers::network_devices_handler::model::NetworkDevicesHandler;
+use crate::handlers::network_devices_handler::endpoints::{init_nd_endpoints, init_nd_legacy_endpoints};
+
+/// Assembles the agent's HTTP API from already constructed handlers. Nothing here touches the
+/// environment or the serial ports, so tests can build the full API around fake devices.
+pub struct ServerBuilder {
+    config: ConfigHandler,
+    devices_handler: NetworkDevicesHandler,
+    handlebars: Option<Handlebars<'static>>,
+    template_handler: Option<TemplateHandler>,
+    auth_handler: Option<AuthHandler>,
+}
+
+impl ServerBuilder {
+    pub fn new(config: ConfigHandler, devices_handler: NetworkDevicesHandler) -> Self {
+        ServerBuilder {
+            config,
+            devices_handler,
+            handlebars: None,
+            template_handler: None,
+            auth_handler: None,
+        }
+    }
+
+    /// Uses the given templates instead of loading them from `config.templates_loc`.
+    pub fn templates(mut self, handlebars: Handlebars<'static>) -> Self {
+        self.handlebars = Some(handlebars);
+        self
+    }
+
+    /// Uses the given config templates instead of loading them from `config.config_templates_loc`.
+    pub fn config_templates(mut self, template_handler: TemplateHandler) -> Self {
+        self.template_handler = Some(template_handler);
+        self
+    }
+
+    /// Uses the given tokens instead of loading them from `config.tokens_loc`.
+    pub fn auth(mut self, auth_handler: AuthHandler) -> Self {
+        self.auth_handler = Some(auth_handler);
+        self
+    }
+
+    /// Builds an `App` for use with `actix_web::test::init_service`.
+    pub fn app(self) -> std::io::Result<App<impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >>> {
+        let state = self.state()?;
+        Ok(state.app())
+    }
+
+    /// Binds every configured address and returns the server together with the addresses it
+    /// actually bound, which is how callers find the port chosen when `config.port` is 0.
+    pub fn build(self) -> std::io::Result<(Server, Vec<SocketAddr>)> {
+        let bind_addresses = self.config.bind_addresses.clone();
+        let port = self.config.port;
+        if bind_addresses.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "No bind addresses configured"));
+        }
+
+        let tls_config = if self.config.tls {
+            Some(tls::server_config(&self.config)?)
+        } else {
+            None
+        };
+
+        let mut state = self.state()?;
+        if state.config.terminal {
+            let terminal_handler = TerminalHandler::start(&state.config, state.devices_handler.clone(), state.auth_handler.clone())?;
+            state.terminal_handler = Data::new(terminal_handler);
+        }
+        let mut server = HttpServer::new(move || state.app()).on_connect(tls::on_connect);
+        for address in bind_addresses {
+            server = match &tls_config {
+                Some(tls_config) => server.bind_rustls_021((address, port), tls_config.clone())?,
+                None => server.bind((address, port))?,
+            };
+        }
+        let addrs = server.addrs();
+
+        Ok((server.run(), addrs))
+    }
+
+    fn state(self) -> std::io::Result<AppState> {
+        let handlebars = match self.handlebars {
+            Some(handlebars) => handlebars,
+            None => {
+                let mut handlebars = Handlebars::new();
+                handlebars.register_templates_directory(".html", &self.config.templates_loc)
+                    .map_err(|why| std::io::Error::new(std::io::ErrorKind::NotFound, why))?;
+                handlebars
+            }
+        };
+
+        let template_handler = match self.template_handler {
+            Some(template_handler) => template_handler,
+            None => TemplateHandler::load(&self.config.config_templates_loc)
+                .map_err(|why| std::io::Error::new(std::io::ErrorKind::InvalidData, why))?,
+        };
+
+        let auth_handler = match self.auth_handler {
+            Some(auth_handler) => auth_handler,
+            None if self.config.auth => AuthHandler::load(&self.config.tokens_loc)?,
+            None => {
+                warn!("Authentication is turned off, the API is open to anyone who can reach it");
+                AuthHandler::disabled()
+            }
+        };
+
+        Ok(AppState {
+            agent_id: self.config.uuid.clone(),
+            auth_handler: Data::new(auth_handler),
+            handlebars: Data::new(handlebars),
+            template_handler: Data::new(template_handler),
+            config: Data::new(self.config),
+            devices_handler: Data::new(self.devices_handler),
+            terminal_handler: Data::new(TerminalHandler::default()),
+        })
+    }
+}
+
+/// Shared between all workers, so every worker sees the same devices.
+#[derive(Clone)]
+struct AppState {
+    agent_id: String,
+    auth_handler: Data<AuthHandler>,
+    handlebars: Data<Handlebars<'static>>,
+    template_handler: Data<TemplateHandler>,
+    config: Data<ConfigHandler>,
+    devices_handler: Data<NetworkDevicesHandler>,
+    terminal_handler: Data<TerminalHandler>,
+}
+
+impl AppState {
+    fn app(&self) -> App<impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >> {
+        let auth_handler = self.auth_handler.clone();
+        App::new()
+            .app_data(self.auth_handler.clone())
+            .app_data(self.handlebars.clone())
+            .app_data(self.template_handler.clone())
+            .app_data(self.config.clone())
+            .app_data(self.devices_handler.clone())
+            .app_data(self.terminal_handler.clone())
+            .wrap_fn(move |req, srv| {
+                let call = match auth_handler.authenticate(&req) {
+                    Ok(()) => Ok(srv.call(req)),
+                    // browsers are sent to the login form instead of being asked for a bearer token
+                    Err(_) if is_dashboard(req.path()) => Err(req.into_response(HttpResponse::SeeOther()
+                        .insert_header((LOCATION, LOGIN_PATH))
+                        .finish())),
+                    Err(why) => Err(req.error_response(why)),
+                };
+                async move {
+                    match call {
+                        Ok(call) => call.await.map(ServiceResponse::map_into_left_body),
+                        Err(response) => Ok(response.map_into_right_body()),
+                    }
+                }
+            })
+            .wrap(Logger::default())
+            .wrap(DefaultHeaders::new().add(("X-Agent-Id", self.agent_id.clone())))
+            .service(health)
+            .service(SwaggerUi::new("/swagger-ui/{_:.*}").url("/openapi.json", ApiDoc::openapi()))
+            .service(web::scope("/api/v1")
+                .configure(init_nd_endpoints)
+                .configure(init_ch_endpoints)
+                .configure(init_bh_endpoints)
+                .configure(init_th_endpoints)
+                .configure(init_ah_endpoints)
+                .configure(init_co_endpoints)
+                .configure(init_te_endpoints)
+                .configure(init_rh_endpoints))
+            .configure(init_nd_legacy_endpoints)
+            .configure(init_ch_legacy_endpoints)
+            .configure(init_bh_legacy_endpoints)
+            .configure(init_th_legacy_endpoints)
+            .configure(init_ah_legacy_endpoints)
+            .configure(init_dh_endpoints)
+            .default_service(web::route().to(not_found::not_found))
+    }
+}
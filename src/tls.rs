@@ -0,0 +1,129 @@
+use std::any::Any;
+use std::fs;
+use std::io::{self, BufReader};
+use std::net::IpAddr;
+use std::path::Path;
+use actix_tls::accept::rustls_0_21::TlsStream;
+use actix_web::dev::Extensions;
+use actix_web::rt::net::TcpStream;
+use log::info;
+use rcgen::{CertificateParams, SanType};
+use rustls::server::AllowAnyAnonymousOrAuthenticatedClient;
+use rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+
+use crate::handlers::config_handler::model::ConfigHandler;
+
+/// Marks connections whose client presented a certificate signed by `tls_client_ca_loc`.
+#[derive(Debug, Clone)]
+pub(crate) struct ClientCertificate;
+
+/// TLS settings for the server, generating a self-signed certificate on first boot when neither
+/// `tls_cert_loc` nor `tls_key_loc` exists. Client certificates are checked against
+/// `tls_client_ca_loc` when it's set, clients without one still connect and authenticate with
+/// a token.
+pub(crate) fn server_config(config: &ConfigHandler) -> io::Result<ServerConfig> {
+    match (Path::new(&config.tls_cert_loc).exists(), Path::new(&config.tls_key_loc).exists()) {
+        (false, false) => generate_self_signed(config)?,
+        (true, false) => return Err(missing_pair(&config.tls_key_loc, &config.tls_cert_loc)),
+        (false, true) => return Err(missing_pair(&config.tls_cert_loc, &config.tls_key_loc)),
+        (true, true) => {}
+    }
+    let certs = read_certs(&config.tls_cert_loc)?;
+    let key = read_key(&config.tls_key_loc)?;
+
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match &config.tls_client_ca_loc {
+        Some(ca_loc) => {
+            let mut roots = RootCertStore::empty();
+            for cert in read_certs(ca_loc)? {
+                roots.add(&cert).map_err(invalid_data)?;
+            }
+            builder.with_client_cert_verifier(AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed())
+        }
+        None => builder.with_no_client_auth(),
+    };
+    builder.with_single_cert(certs, key).map_err(invalid_data)
+}
+
+/// `HttpServer::on_connect` callback recording verified client certificates.
+pub(crate) fn on_connect(connection: &dyn Any, data: &mut Extensions) {
+    if let Some(stream) = connection.downcast_ref::<TlsStream<TcpStream>>() {
+        let (_, session) = stream.get_ref();
+        if session.peer_certificates().is_some_and(|certs| !certs.is_empty()) {
+            data.insert(ClientCertificate);
+        }
+    }
+}
+
+/// Certificate for `localhost` and the addresses the agent is reached on. Wildcard bind
+/// addresses aren't names anyone connects to, so they're left out.
+fn generate_self_signed(config: &ConfigHandler) -> io::Result<()> {
+    let mut names: Vec<SanType> = Vec::new();
+    let addresses = [config.ip_address.clone()].into_iter().chain(config.bind_addresses.iter().cloned());
+    for name in ["localhost".to_string()].into_iter().chain(addresses) {
+        let name = match name.parse::<IpAddr>() {
+            Ok(ip) if ip.is_unspecified() => continue,
+            Ok(ip) => SanType::IpAddress(ip),
+            Err(_) => SanType::DnsName(name),
+        };
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    let mut params = CertificateParams::default();
+    params.subject_alt_names = names;
+    let cert = rcgen::Certificate::from_params(params).map_err(invalid_data)?;
+
+    for path in [&config.tls_cert_loc, &config.tls_key_loc] {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    fs::write(&config.tls_cert_loc, cert.serialize_pem().map_err(invalid_data)?)?;
+    write_private(&config.tls_key_loc, &cert.serialize_private_key_pem())?;
+    info!("Generated self-signed certificate {}", config.tls_cert_loc);
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_private(path: &str, content: &str) -> io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)?
+        .write_all(content.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &str, content: &str) -> io::Result<()> {
+    fs::write(path, content)
+}
+
+fn read_certs(path: &str) -> io::Result<Vec<Certificate>> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(fs::File::open(path)?))?;
+    if certs.is_empty() {
+        return Err(invalid_data(format!("No certificates in {}", path)));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn read_key(path: &str) -> io::Result<PrivateKey> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    while let Some(item) = rustls_pemfile::read_one(&mut reader)? {
+        match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => {}
+        }
+    }
+    Err(invalid_data(format!("No private key in {}", path)))
+}
+
+/// Generating the missing half would leave a certificate and key that don't belong together.
+fn missing_pair(missing: &str, existing: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{} is missing but {} exists, provide both or neither", missing, existing))
+}
+
+fn invalid_data<E: ToString>(why: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, why.to_string())
+}
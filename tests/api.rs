@@ -0,0 +1,133 @@
+mod common;
+
+use std::collections::HashMap;
+use std::time::Duration;
+use actix_web::test;
+use rpi_client::{NetworkDevice, NetworkDevicesHandler, ServerBuilder};
+
+use common::{fake_device, test_app, test_config, FakeConsole};
+
+#[actix_web::test]
+async fn lists_injected_devices() {
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", FakeConsole::default())])).await;
+
+    let req = test::TestRequest::get().uri("/devices").to_request();
+    let devices: HashMap<u32, NetworkDevice> = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(1, devices.len());
+    assert_eq!("lab-sw1", devices[&1].hostname);
+}
+
+#[actix_web::test]
+async fn unknown_device_is_an_error() {
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", FakeConsole::default())])).await;
+
+    let req = test::TestRequest::get().uri("/device/42").to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert!(resp.status().is_client_error());
+}
+
+#[actix_web::test]
+async fn responses_carry_agent_id() {
+    let conf = test_config();
+    let agent_id = conf.uuid.clone();
+    let app = test::init_service(ServerBuilder::new(conf, NetworkDevicesHandler::new(HashMap::new())).app().unwrap()).await;
+
+    let req = test::TestRequest::get().uri("/config").to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(agent_id, resp.headers().get("X-Agent-Id").unwrap().to_str().unwrap());
+    let config: rpi_client::ConfigHandler = test::read_body_json(resp).await;
+    assert_eq!(agent_id, config.uuid);
+}
+
+#[actix_web::test]
+async fn slow_device_does_not_block_others() {
+    let slow = FakeConsole::default().delayed(Duration::from_secs(2));
+    let app = test::init_service(test_app(vec![
+        fake_device("slow-sw", slow),
+        fake_device("fast-sw", FakeConsole::default()),
+    ])).await;
+
+    let slow_req = test::TestRequest::get().uri("/device/1/reload_configs").to_request();
+    let fast_req = test::TestRequest::get().uri("/device/2").to_request();
+
+    tokio::select! {
+        _ = test::call_service(&app, slow_req) => panic!("Slow device answered first"),
+        resp = test::call_service(&app, fast_req) => assert!(resp.status().is_success()),
+    }
+}
+
+#[actix_web::test]
+async fn hostname_change_is_sent_to_console() {
+    let console = FakeConsole::default();
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::post().uri("/device/1/hostname/core-sw1").to_request();
+    let device: NetworkDevice = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!("core-sw1", device.hostname);
+    assert!(console.sent().iter().any(|sent| sent.contains("core-sw1")));
+}
+
+#[actix_web::test]
+async fn listing_does_not_wait_for_busy_device() {
+    let slow = FakeConsole::default().delayed(Duration::from_secs(2));
+    let app = test::init_service(test_app(vec![fake_device("slow-sw", slow)])).await;
+
+    let slow_req = test::TestRequest::get().uri("/device/1/reload_configs").to_request();
+    let list_req = test::TestRequest::get().uri("/devices").to_request();
+
+    tokio::select! {
+        biased;
+        _ = test::call_service(&app, slow_req) => panic!("Slow device answered first"),
+        resp = test::call_service(&app, list_req) => assert!(resp.status().is_success()),
+    }
+}
+
+#[actix_web::test]
+async fn busy_device_can_refuse_instead_of_waiting() {
+    let slow = FakeConsole::default().delayed(Duration::from_secs(2));
+    let app = test::init_service(test_app(vec![fake_device("slow-sw", slow)])).await;
+
+    let slow_req = test::TestRequest::get().uri("/device/1/reload_configs").to_request();
+    let busy_req = test::TestRequest::post().uri("/device/1/hostname/other?wait=false").to_request();
+
+    tokio::select! {
+        biased;
+        _ = test::call_service(&app, slow_req) => panic!("Slow device answered first"),
+        resp = test::call_service(&app, busy_req) => assert_eq!(423, resp.status().as_u16()),
+    }
+}
+
+#[actix_web::test]
+async fn dry_run_returns_commands_without_sending() {
+    let console = FakeConsole::default();
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::post().uri("/device/1/hostname/core-sw1?dry_run=true").to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(serde_json::json!(["en", "conf t", "hostname core-sw1", "end"]), body["commands"]);
+    assert!(console.sent().is_empty());
+    let req = test::TestRequest::get().uri("/device/1").to_request();
+    let device: NetworkDevice = test::call_and_read_body_json(&app, req).await;
+    assert_eq!("lab-sw1", device.hostname);
+}
+
+#[actix_web::test]
+async fn dry_run_lists_what_the_change_sends() {
+    let console = FakeConsole::default();
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+    let vlan = serde_json::json!({ "number": 20, "name": "voice", "interfaces": [] });
+
+    let req = test::TestRequest::post().uri("/device/1/vlan?dry_run=true").set_json(&vlan).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::post().uri("/device/1/vlan").set_json(&vlan).to_request();
+    test::call_service(&app, req).await;
+
+    let planned: Vec<String> = serde_json::from_value(body["commands"].clone()).unwrap();
+    let sent: Vec<String> = console.sent()[0].lines().map(String::from).collect();
+    assert_eq!(planned, sent);
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use std::collections::{BTreeMap, HashMap};
+use actix_web::http::StatusCode;
+use actix_web::test;
+use rpi_client::{Interface, NetworkDevice};
+use serde_json::{json, Value};
+
+use common::{fake_device, test_app, FakeConsole};
+
+fn switch_with_port(console: FakeConsole) -> NetworkDevice {
+    let mut device = fake_device("lab-sw1", console);
+    device.interfaces = HashMap::from([(7, Interface {
+        int_type: "FastEthernet".to_string(),
+        module: 0,
+        number: 1,
+        ip_address: "unassigned".to_string(),
+        status: "down".to_string(),
+    })]);
+    device
+}
+
+#[actix_web::test]
+async fn hostname_is_changed_with_a_json_body() {
+    let console = FakeConsole::default();
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::patch().uri("/api/v1/devices/1")
+        .set_json(json!({ "hostname": "core-sw1" }))
+        .to_request();
+    let device: NetworkDevice = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!("core-sw1", device.hostname);
+    assert!(console.sent().iter().any(|sent| sent.contains("hostname core-sw1")));
+}
+
+#[actix_web::test]
+async fn interfaces_are_addressed_by_name() {
+    let app = test::init_service(test_app(vec![switch_with_port(FakeConsole::default())])).await;
+
+    let req = test::TestRequest::get().uri("/api/v1/devices/1/interfaces").to_request();
+    let interfaces: BTreeMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(vec!["FastEthernet0/1"], interfaces.keys().collect::<Vec<_>>());
+
+    let interface = json!({ "ip_address": "10.0.0.1", "mask": "255.255.255.0", "status": "up" });
+    for uri in ["/api/v1/devices/1/interfaces/FastEthernet0/1?dry_run=true", "/api/v1/devices/1/interfaces/fastethernet0%2F1?dry_run=true"] {
+        let req = test::TestRequest::put().uri(uri).set_json(&interface).to_request();
+        let dry_run: Value = test::call_and_read_body_json(&app, req).await;
+        assert!(dry_run["commands"].as_array().unwrap().contains(&json!("interface FastEthernet 0/1")), "{}", uri);
+    }
+
+    let req = test::TestRequest::put().uri("/api/v1/devices/1/interfaces/GigabitEthernet0/1").set_json(&interface).to_request();
+    assert_eq!(StatusCode::BAD_REQUEST, test::call_service(&app, req).await.status());
+}
+
+#[actix_web::test]
+async fn config_reload_needs_post() {
+    let console = FakeConsole::default().respond("sh running-config", "hostname lab-sw1");
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::get().uri("/api/v1/devices/1/config/reload").to_request();
+    assert_eq!(StatusCode::NOT_FOUND, test::call_service(&app, req).await.status());
+    assert!(console.sent().is_empty());
+
+    let req = test::TestRequest::post().uri("/api/v1/devices/1/config/reload").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert!(console.sent().iter().any(|sent| sent.contains("sh running-config")));
+}
+
+#[actix_web::test]
+async fn old_routes_are_deprecated_aliases() {
+    let console = FakeConsole::default();
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::post().uri("/device/1/hostname/core-sw1").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!("true", resp.headers().get("Deprecation").unwrap());
+
+    let req = test::TestRequest::get().uri("/api/v1/devices/1").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.headers().get("Deprecation").is_none());
+    let device: NetworkDevice = test::read_body_json(resp).await;
+    assert_eq!("core-sw1", device.hostname);
+}
+
+#[actix_web::test]
+async fn unknown_paths_still_hit_the_not_found_page() {
+    let app = test::init_service(test_app(vec![])).await;
+
+    for uri in ["/no/such/page", "/api/v1/no/such/page"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(StatusCode::NOT_FOUND, resp.status(), "{}", uri);
+        assert!(resp.headers().get("Deprecation").is_none(), "{}", uri);
+    }
+}
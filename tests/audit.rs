@@ -0,0 +1,87 @@
+mod common;
+
+use actix_web::test;
+use actix_web::http::header::AUTHORIZATION;
+use rpi_client::{ApiToken, AuditHandler, AuthHandler, Role, ServerBuilder};
+use serde_json::Value;
+
+use common::{devices_handler, fake_device, test_app_with, test_config, FakeConsole};
+
+#[actix_web::test]
+async fn commands_are_audited_per_device() {
+    let audit_dir = tempfile::tempdir().unwrap();
+    let console = FakeConsole::default()
+        .respond("en", "% Invalid input detected at '^' marker.");
+    let handler = devices_handler(vec![
+        fake_device("lab-sw1", console),
+        fake_device("lab-sw2", FakeConsole::default()),
+    ]).with_audit(AuditHandler::new(audit_dir.path().to_str().unwrap()));
+    let app = test::init_service(test_app_with(handler)).await;
+
+    let req = test::TestRequest::post().uri("/device/1/hostname/core-sw1").to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::get().uri("/device/2/reload_configs").to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::get().uri("/audit?device=1").to_request();
+    let entries: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(1, entries.len());
+    assert_eq!("POST /device/1/hostname/core-sw1", entries[0]["endpoint"]);
+    assert_eq!(serde_json::json!(["en", "conf t", "hostname core-sw1", "end"]), entries[0]["commands"]);
+    assert_eq!("ios_error", entries[0]["outcome"]);
+    assert_eq!("lab-sw1", entries[0]["hostname"]);
+
+    let req = test::TestRequest::get().uri("/audit").to_request();
+    let entries: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert!(entries.len() > 1);
+    assert!(entries.iter().skip(1).all(|entry| entry["device_id"] == 2));
+
+    let req = test::TestRequest::get().uri("/audit?since=2999-01-01T00:00:00Z").to_request();
+    let entries: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert!(entries.is_empty());
+}
+
+#[actix_web::test]
+async fn audit_log_is_rotated() {
+    let audit_dir = tempfile::tempdir().unwrap();
+    let handler = devices_handler(vec![fake_device("lab-sw1", FakeConsole::default())])
+        .with_audit(AuditHandler::new(audit_dir.path().to_str().unwrap()).with_rotation(1, 2));
+    let app = test::init_service(test_app_with(handler)).await;
+
+    for hostname in ["a", "b", "c", "d"] {
+        let req = test::TestRequest::post().uri(&format!("/device/1/hostname/{}", hostname)).to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+
+    assert!(audit_dir.path().join("audit.jsonl.2").exists());
+    assert!(!audit_dir.path().join("audit.jsonl.3").exists());
+    let req = test::TestRequest::get().uri("/audit").to_request();
+    let entries: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    let hostnames: Vec<&str> = entries.iter().map(|entry| entry["commands"][2].as_str().unwrap()).collect();
+    assert_eq!(vec!["hostname b", "hostname c", "hostname d"], hostnames);
+
+    // the newest ones, still oldest first, across the rotated files
+    let req = test::TestRequest::get().uri("/audit?limit=2").to_request();
+    let entries: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    let hostnames: Vec<&str> = entries.iter().map(|entry| entry["commands"][2].as_str().unwrap()).collect();
+    assert_eq!(vec!["hostname c", "hostname d"], hostnames);
+}
+
+#[actix_web::test]
+async fn only_admins_may_read_the_audit_log() {
+    let audit_dir = tempfile::tempdir().unwrap();
+    let handler = devices_handler(vec![fake_device("lab-sw1", FakeConsole::default())])
+        .with_audit(AuditHandler::new(audit_dir.path().to_str().unwrap()));
+    let auth = AuthHandler::new(vec![
+        ApiToken::new("instructor", "s3cret", Role::Admin),
+        ApiToken::new("tutor", "tutor-token", Role::Operator),
+    ]);
+    let app = test::init_service(ServerBuilder::new(test_config(), handler).auth(auth).app().unwrap()).await;
+
+    for uri in ["/api/v1/audit", "/audit"] {
+        let req = test::TestRequest::get().uri(uri).insert_header((AUTHORIZATION, "Bearer tutor-token")).to_request();
+        assert_eq!(403, test::call_service(&app, req).await.status().as_u16());
+        let req = test::TestRequest::get().uri(uri).insert_header((AUTHORIZATION, "Bearer s3cret")).to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use actix_web::test;
+use actix_web::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use rpi_client::{ApiToken, AuthHandler, ConfigHandler, Role, ServerBuilder};
+
+use common::{devices_handler, fake_device, test_config, FakeConsole};
+
+fn auth() -> AuthHandler {
+    AuthHandler::new(vec![
+        ApiToken::new("instructor", "s3cret", Role::Admin),
+        ApiToken::new("student", "student-token", Role::Viewer),
+        ApiToken::new("tutor", "tutor-token", Role::Operator),
+    ])
+}
+
+#[actix_web::test]
+async fn requests_without_token_are_rejected() {
+    let console = FakeConsole::default();
+    let app = test::init_service(ServerBuilder::new(test_config(), devices_handler(vec![fake_device("lab-sw1", console.clone())]))
+        .auth(auth())
+        .app().unwrap()).await;
+
+    let req = test::TestRequest::delete().uri("/device/1/vlan/10").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(401, resp.status().as_u16());
+    assert_eq!("Bearer", resp.headers().get(WWW_AUTHENTICATE).unwrap());
+
+    let req = test::TestRequest::get().uri("/devices")
+        .insert_header((AUTHORIZATION, "Bearer wrong"))
+        .to_request();
+    assert_eq!(401, test::call_service(&app, req).await.status().as_u16());
+    assert!(console.sent().is_empty());
+}
+
+#[actix_web::test]
+async fn valid_token_is_accepted() {
+    assert_ne!("s3cret", ApiToken::new("instructor", "s3cret", Role::Admin).hash);
+    let app = test::init_service(ServerBuilder::new(test_config(), devices_handler(vec![fake_device("lab-sw1", FakeConsole::default())]))
+        .auth(auth())
+        .app().unwrap()).await;
+
+    let req = test::TestRequest::get().uri("/devices")
+        .insert_header((AUTHORIZATION, "Bearer s3cret"))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+}
+
+#[actix_web::test]
+async fn health_stays_public() {
+    let app = test::init_service(ServerBuilder::new(test_config(), devices_handler(vec![]))
+        .auth(auth())
+        .app().unwrap()).await;
+
+    let req = test::TestRequest::get().uri("/status/health").to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+}
+
+#[actix_web::test]
+async fn roles_limit_what_tokens_may_do() {
+    let console = FakeConsole::default();
+    let app = test::init_service(ServerBuilder::new(test_config(), devices_handler(vec![fake_device("lab-sw1", console.clone())]))
+        .auth(auth())
+        .app().unwrap()).await;
+    let restore = serde_json::json!({ "config": "hostname lab-sw1\n" });
+
+    let req = test::TestRequest::get().uri("/device/1").insert_header((AUTHORIZATION, "Bearer student-token")).to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::post().uri("/device/1/hostname/core-sw1").insert_header((AUTHORIZATION, "Bearer student-token")).to_request();
+    assert_eq!(403, test::call_service(&app, req).await.status().as_u16());
+    let req = test::TestRequest::post().uri("/device/1/config/restore").set_json(&restore)
+        .insert_header((AUTHORIZATION, "Bearer tutor-token")).to_request();
+    assert_eq!(403, test::call_service(&app, req).await.status().as_u16());
+    assert!(console.sent().is_empty());
+
+    let req = test::TestRequest::post().uri("/device/1/hostname/core-sw1").insert_header((AUTHORIZATION, "Bearer tutor-token")).to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::post().uri("/device/1/config/restore").set_json(&restore)
+        .insert_header((AUTHORIZATION, "Bearer s3cret")).to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+}
+
+#[actix_web::test]
+async fn operators_cannot_smuggle_cli_lines_into_values() {
+    let console = FakeConsole::default();
+    let app = test::init_service(ServerBuilder::new(test_config(), devices_handler(vec![fake_device("lab-sw1", console.clone())]))
+        .auth(auth())
+        .app().unwrap()).await;
+
+    let long_hostname = "a".repeat(64);
+    for hostname in ["x\nenable secret pwned", "x\renable secret pwned", "core sw1", "x\u{1a}", "", long_hostname.as_str()] {
+        for uri in ["/api/v1/devices/1", "/api/v1/devices/1?dry_run=true"] {
+            let req = test::TestRequest::patch().uri(uri).set_json(serde_json::json!({ "hostname": hostname }))
+                .insert_header((AUTHORIZATION, "Bearer tutor-token")).to_request();
+            assert_eq!(400, test::call_service(&app, req).await.status().as_u16(), "hostname {:?}", hostname);
+        }
+    }
+    let req = test::TestRequest::post().uri("/api/v1/devices/1/vlans")
+        .set_json(serde_json::json!({ "number": 10, "name": "users\nenable secret pwned", "interfaces": [] }))
+        .insert_header((AUTHORIZATION, "Bearer tutor-token")).to_request();
+    assert_eq!(400, test::call_service(&app, req).await.status().as_u16());
+    let changes = serde_json::json!({ "changes": [
+        { "op": "add_vlan", "number": 10, "name": "users", "interfaces": [] },
+        { "op": "hostname", "hostname": "x\nusername admin privilege 15 secret pwned" },
+    ]});
+    let req = test::TestRequest::post().uri("/api/v1/devices/1/changes").set_json(&changes)
+        .insert_header((AUTHORIZATION, "Bearer tutor-token")).to_request();
+    assert_eq!(400, test::call_service(&app, req).await.status().as_u16());
+    assert!(console.sent().is_empty());
+
+    let req = test::TestRequest::patch().uri("/api/v1/devices/1").set_json(serde_json::json!({ "hostname": "core-sw1" }))
+        .insert_header((AUTHORIZATION, "Bearer tutor-token")).to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+}
+
+#[actix_web::test]
+async fn missing_tokens_file_does_not_turn_authentication_off() {
+    let dir = tempfile::tempdir().unwrap();
+    let conf = ConfigHandler {
+        auth: true,
+        tokens_loc: dir.path().join("api_tokens.json").to_str().unwrap().to_string(),
+        ..test_config()
+    };
+    assert!(ServerBuilder::new(conf.clone(), devices_handler(vec![])).app().is_err());
+
+    let tokens = serde_json::to_string(&[ApiToken::new("instructor", "s3cret", Role::Admin)]).unwrap();
+    std::fs::write(&conf.tokens_loc, tokens).unwrap();
+    let app = test::init_service(ServerBuilder::new(conf, devices_handler(vec![])).app().unwrap()).await;
+    let req = test::TestRequest::get().uri("/api/v1/devices").to_request();
+    assert_eq!(401, test::call_service(&app, req).await.status().as_u16());
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use actix_web::test;
+use rpi_client::BackupHandler;
+use serde_json::Value;
+
+use common::{devices_handler, fake_device, test_app_with, FakeConsole};
+
+const CONFIG_V1: &str = "Building configuration...\n!\nhostname lab-sw1\n!\ninterface FastEthernet0/1\n shutdown\n!\nend\nlab-sw1#";
+const CONFIG_V2: &str = "Building configuration...\n!\nhostname lab-sw1\n!\ninterface FastEthernet0/1\n no shutdown\n!\nend\nlab-sw1#";
+
+#[actix_web::test]
+async fn identical_configs_are_stored_once() {
+    let backup_dir = tempfile::tempdir().unwrap();
+    let console = FakeConsole::default()
+        .respond("sh running-config", CONFIG_V1)
+        .respond("sh startup-config", CONFIG_V1);
+    let mut device = fake_device("lab-sw1", console.clone());
+    device.serial_number = "FOC1234X0AB".to_string();
+    let handler = devices_handler(vec![device]).with_backups(BackupHandler::new(backup_dir.path().to_str().unwrap()));
+    let app = test::init_service(test_app_with(handler)).await;
+
+    for _ in 0..2 {
+        let req = test::TestRequest::get().uri("/device/1/reload_configs").to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+    console.set_response("sh running-config", CONFIG_V2);
+    let req = test::TestRequest::get().uri("/device/1/reload_configs").to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::get().uri("/device/1/backups?kind=running").to_request();
+    let versions: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(2, versions.len());
+    assert!(backup_dir.path().join("FOC1234X0AB").is_dir());
+
+    let req = test::TestRequest::get().uri("/device/1/backups?kind=startup").to_request();
+    let startup: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(1, startup.len());
+
+    let first = versions[0]["id"].as_str().unwrap();
+    let second = versions[1]["id"].as_str().unwrap();
+    let req = test::TestRequest::get().uri(&format!("/device/1/backups/{}", first)).to_request();
+    let text = test::call_and_read_body(&app, req).await;
+    assert!(String::from_utf8(text.to_vec()).unwrap().contains(" shutdown"));
+
+    let req = test::TestRequest::get().uri(&format!("/device/1/backups/diff?from={}&to={}", first, second)).to_request();
+    let diff: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(diff["diff"].as_str().unwrap().contains("@@ interface FastEthernet0/1 @@\n interface FastEthernet0/1\n- shutdown\n+ no shutdown\n"));
+}
+
+#[actix_web::test]
+async fn unknown_version_is_an_error() {
+    let backup_dir = tempfile::tempdir().unwrap();
+    let handler = devices_handler(vec![fake_device("lab-sw1", FakeConsole::default())])
+        .with_backups(BackupHandler::new(backup_dir.path().to_str().unwrap()));
+    let app = test::init_service(test_app_with(handler)).await;
+
+    let req = test::TestRequest::get().uri("/device/1/backups/../../etc/passwd").to_request();
+    assert!(!test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::get().uri("/device/1/backups/running-20260101T000000.000Z-000000000000").to_request();
+    assert!(test::call_service(&app, req).await.status().is_client_error());
+}
+
+#[actix_web::test]
+async fn versions_can_not_leave_the_device_directory() {
+    let backup_dir = tempfile::tempdir().unwrap();
+    let backups = BackupHandler::new(backup_dir.path().to_str().unwrap());
+    let device = fake_device("lab-sw1", FakeConsole::default());
+    std::fs::create_dir_all(backups.device_dir(&device).join("running-x")).unwrap();
+    std::fs::write(backup_dir.path().join("secret-000000000000.cfg"), "secret").unwrap();
+
+    for version in ["running-x/../../secret-000000000000", "running-..-000000000000", "running-20260101T000000Z-000000000000"] {
+        let error = backups.read(&device, version).unwrap_err();
+        assert!(error.message.contains("not a config version"), "{}: {}", version, error.message);
+    }
+}
+
+#[actix_web::test]
+async fn changed_configs_are_committed_to_git() {
+    let backup_dir = tempfile::tempdir().unwrap();
+    let console = FakeConsole::default()
+        .respond("sh running-config", CONFIG_V1)
+        .respond("sh startup-config", CONFIG_V1);
+    let handler = devices_handler(vec![fake_device("lab-sw1", console.clone())])
+        .with_backups(BackupHandler::new(backup_dir.path().to_str().unwrap()).with_git(true));
+    let app = test::init_service(test_app_with(handler)).await;
+
+    let req = test::TestRequest::get().uri("/device/1/reload_configs").to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    console.set_response("sh running-config", CONFIG_V2);
+    let req = test::TestRequest::get().uri("/device/1/config/running?refresh=true").to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let history = backup_dir.path().join("history");
+    let running = std::fs::read_to_string(history.join("lab-sw1-running.cfg")).unwrap();
+    assert!(running.contains(" no shutdown"));
+    assert!(history.join("lab-sw1-startup.cfg").exists());
+
+    let log = std::process::Command::new("git")
+        .arg("-C").arg(&history)
+        .args(["log", "--format=%B"])
+        .output()
+        .unwrap();
+    let log = String::from_utf8(log.stdout).unwrap();
+    assert_eq!(2, log.matches("lab-sw1 (lab-sw1)").count());
+    assert!(log.contains("Triggered by GET /device/1/config/running?refresh=true"));
+    assert!(log.contains("Triggered by GET /device/1/reload_configs"));
+}
+
+#[actix_web::test]
+async fn history_catches_up_with_configs_archived_before_git() {
+    let backup_dir = tempfile::tempdir().unwrap();
+    let console = FakeConsole::default()
+        .respond("sh running-config", CONFIG_V1)
+        .respond("sh startup-config", CONFIG_V1);
+    for git in [false, true, true] {
+        let handler = devices_handler(vec![fake_device("lab-sw1", console.clone())])
+            .with_backups(BackupHandler::new(backup_dir.path().to_str().unwrap()).with_git(git));
+        let app = test::init_service(test_app_with(handler)).await;
+        let req = test::TestRequest::get().uri("/device/1/reload_configs").to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+
+    // the archive already had both configs, the history gets them anyway, once
+    let history = backup_dir.path().join("history");
+    assert!(std::fs::read_to_string(history.join("lab-sw1-running.cfg")).unwrap().contains(" shutdown"));
+    let log = std::process::Command::new("git")
+        .arg("-C").arg(&history)
+        .args(["log", "--format=%s"])
+        .output()
+        .unwrap();
+    assert_eq!("lab-sw1 (lab-sw1): running and startup config changed\n", String::from_utf8(log.stdout).unwrap());
+}
@@ -0,0 +1,224 @@
+mod common;
+
+use actix_web::test;
+use serde_json::{json, Value};
+use chrono::{Duration, Utc};
+use rpi_client::NetworkDevice;
+
+use common::{fake_device, test_app, FakeConsole};
+
+fn console() -> FakeConsole {
+    FakeConsole::default()
+        .respond("copy running-config flash:rpi-rollback.cfg", "Destination filename [rpi-rollback.cfg]?")
+        .respond("", "2048 bytes copied in 0.120 secs (17066 bytes/sec)\nlab-sw1#")
+}
+
+fn changes() -> Value {
+    json!({ "changes": [
+        { "op": "hostname", "hostname": "core-sw1" },
+        { "op": "add_vlan", "number": 20, "name": "voice", "interfaces": [] },
+    ]})
+}
+
+#[actix_web::test]
+async fn changes_are_applied_in_order() {
+    let console = console();
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::post().uri("/device/1/changes").set_json(changes()).to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(2, report["applied"]);
+    assert!(report["failure"].is_null());
+    let sent = console.sent();
+    let hostname = sent.iter().position(|line| line == "hostname core-sw1\n").unwrap();
+    let vlan = sent.iter().position(|line| line == "vlan 20\n").unwrap();
+    assert!(sent.iter().position(|line| line.starts_with("copy running-config")).unwrap() < hostname);
+    assert!(hostname < vlan);
+    assert!(!sent.iter().any(|line| line.starts_with("configure replace")));
+}
+
+#[actix_web::test]
+async fn failed_change_rolls_back() {
+    let console = console()
+        .respond("vlan 20", "% Invalid input detected at '^' marker.\nlab-sw1(config)#");
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::post().uri("/device/1/changes").set_json(changes()).to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(1, report["applied"]);
+    assert_eq!(2, report["failure"]["step"]);
+    assert_eq!("vlan 20", report["failure"]["line"]);
+    assert_eq!(true, report["rolled_back"]);
+    let sent = console.sent();
+    let vlan = sent.iter().position(|line| line == "vlan 20\n").unwrap();
+    assert!(!sent[vlan..].contains(&"name voice\n".to_string()));
+    assert!(sent[vlan..].contains(&"configure replace flash:rpi-rollback.cfg force\n".to_string()));
+
+    let req = test::TestRequest::get().uri("/device/1").to_request();
+    let device: NetworkDevice = test::call_and_read_body_json(&app, req).await;
+    assert_eq!("lab-sw1", device.hostname);
+}
+
+#[actix_web::test]
+async fn console_failure_mid_change_rolls_back() {
+    let console = console().fail_on("vlan 20");
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::post().uri("/device/1/changes").set_json(changes()).to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(1, report["applied"]);
+    assert_eq!("vlan 20", report["failure"]["line"]);
+    assert!(report["failure"]["error"].as_str().unwrap().contains("Console exchange failed"));
+    assert_eq!(true, report["rolled_back"]);
+    assert!(console.sent().contains(&"configure replace flash:rpi-rollback.cfg force\n".to_string()));
+
+    let req = test::TestRequest::get().uri("/device/1").to_request();
+    let device: NetworkDevice = test::call_and_read_body_json(&app, req).await;
+    assert_eq!("lab-sw1", device.hostname);
+}
+
+#[actix_web::test]
+async fn changes_need_a_snapshot() {
+    let console = FakeConsole::default()
+        .respond("copy running-config flash:rpi-rollback.cfg", "%Error opening flash:rpi-rollback.cfg (No space left on device)");
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::post().uri("/device/1/changes").set_json(changes()).to_request();
+    assert!(test::call_service(&app, req).await.status().is_client_error());
+
+    assert!(!console.sent().contains(&"hostname core-sw1\n".to_string()));
+    let req = test::TestRequest::get().uri("/device/1").to_request();
+    let device: NetworkDevice = test::call_and_read_body_json(&app, req).await;
+    assert_eq!("lab-sw1", device.hostname);
+}
+
+fn confirm_console() -> FakeConsole {
+    FakeConsole::default()
+        .respond("copy running-config flash:rpi-rollback.cfg", "Destination filename [rpi-rollback.cfg]?")
+        .respond("copy running-config startup-config", "Destination filename [startup-config]?")
+        .respond("", "Building configuration...\n[OK]\n2048 bytes copied in 0.120 secs (17066 bytes/sec)\nlab-sw1#")
+        .respond("reload in 5", "System configuration has been modified. Save? [yes/no]:")
+        .respond("no", "Reload scheduled in 5 minutes by console\nProceed with reload? [confirm]")
+}
+
+#[actix_web::test]
+async fn confirmed_change_schedules_reload_first() {
+    let console = confirm_console();
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+    let mut body = changes();
+    body["confirm_minutes"] = json!(5);
+
+    let req = test::TestRequest::post().uri("/device/1/changes").set_json(body).to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(2, report["applied"]);
+    assert!(report["confirm_deadline"].is_string());
+    let sent = console.sent();
+    let reload = sent.iter().position(|line| line == "reload in 5\n").unwrap();
+    assert_eq!("no\n", sent[reload + 1]);
+    assert!(reload < sent.iter().position(|line| line == "hostname core-sw1\n").unwrap());
+    assert!(!sent.iter().any(|line| line.starts_with("copy running-config startup-config")));
+
+    let req = test::TestRequest::post().uri("/device/1/changes/confirm?dry_run=true").to_request();
+    let dry_run: Value = test::call_and_read_body_json(&app, req).await;
+    let commands = dry_run["commands"].as_array().unwrap();
+    assert!(commands.contains(&json!("reload cancel")));
+    assert_eq!(sent.len(), console.sent().len());
+
+    let req = test::TestRequest::post().uri("/device/1/changes/confirm").to_request();
+    let device: NetworkDevice = test::call_and_read_body_json(&app, req).await;
+
+    assert!(device.confirm_deadline.is_none());
+    let sent = console.sent();
+    let cancel = sent.iter().position(|line| line == "reload cancel\n").unwrap();
+    assert!(sent[cancel..].iter().any(|line| line.starts_with("copy running-config startup-config")));
+}
+
+#[actix_web::test]
+async fn confirm_needs_a_pending_change() {
+    let console = confirm_console();
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::post().uri("/device/1/changes/confirm").to_request();
+    assert!(test::call_service(&app, req).await.status().is_client_error());
+    assert!(console.sent().is_empty());
+}
+
+#[actix_web::test]
+async fn failed_rollback_still_cancels_the_reload() {
+    let console = confirm_console()
+        .respond("vlan 20", "% Invalid input detected at '^' marker.\nlab-sw1(config)#")
+        .fail_on("configure replace flash:rpi-rollback.cfg force");
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+    let mut body = changes();
+    body["confirm_minutes"] = json!(5);
+
+    let req = test::TestRequest::post().uri("/device/1/changes").set_json(body).to_request();
+    assert!(test::call_service(&app, req).await.status().is_client_error());
+    let sent = console.sent();
+    let replace = sent.iter().position(|line| line.starts_with("configure replace")).unwrap();
+    assert!(sent[replace..].contains(&"reload cancel\n".to_string()));
+}
+
+#[actix_web::test]
+async fn reload_that_could_not_be_cancelled_stays_pending() {
+    let console = confirm_console()
+        .fail_on("vlan 20")
+        .fail_on("configure replace flash:rpi-rollback.cfg force")
+        .fail_on("reload cancel");
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+    let mut body = changes();
+    body["confirm_minutes"] = json!(5);
+
+    let req = test::TestRequest::post().uri("/device/1/changes").set_json(body).to_request();
+    assert!(test::call_service(&app, req).await.status().is_client_error());
+
+    let req = test::TestRequest::get().uri("/device/1").to_request();
+    let device: NetworkDevice = test::call_and_read_body_json(&app, req).await;
+    assert!(device.confirm_deadline.is_some());
+}
+
+#[actix_web::test]
+async fn expired_confirmation_rereads_the_reverted_device() {
+    let console = console()
+        .respond("sh running-config", "hostname lab-sw1\n!\nend\nlab-sw1#");
+    let device = NetworkDevice {
+        confirm_deadline: Some(Utc::now() - Duration::minutes(1)),
+        ..fake_device("core-sw1", console.clone())
+    };
+    let app = test::init_service(test_app(vec![device])).await;
+
+    let req = test::TestRequest::post().uri("/device/1/changes").set_json(changes()).to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(2, report["applied"]);
+    let sent = console.sent();
+    let reread = sent.iter().position(|line| line == "sh running-config\n").unwrap();
+    assert!(reread < sent.iter().position(|line| line == "hostname core-sw1\n").unwrap());
+
+    let req = test::TestRequest::get().uri("/device/1").to_request();
+    let device: NetworkDevice = test::call_and_read_body_json(&app, req).await;
+    assert!(device.confirm_deadline.is_none());
+}
+
+#[actix_web::test]
+async fn late_confirmation_rereads_the_reverted_device() {
+    let console = confirm_console()
+        .respond("sh running-config", "hostname lab-sw1\n!\nend\nlab-sw1#");
+    let device = NetworkDevice {
+        confirm_deadline: Some(Utc::now() - Duration::minutes(1)),
+        ..fake_device("core-sw1", console.clone())
+    };
+    let app = test::init_service(test_app(vec![device])).await;
+
+    let req = test::TestRequest::post().uri("/device/1/changes/confirm").to_request();
+    assert!(test::call_service(&app, req).await.status().is_client_error());
+    assert!(!console.sent().contains(&"reload cancel\n".to_string()));
+
+    let req = test::TestRequest::get().uri("/device/1").to_request();
+    let device: NetworkDevice = test::call_and_read_body_json(&app, req).await;
+    assert_eq!("lab-sw1", device.hostname);
+    assert!(device.confirm_deadline.is_none());
+}
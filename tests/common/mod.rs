@@ -0,0 +1,123 @@
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use actix_web::App;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use rpi_client::{Console, ConfigHandler, ConsoleHandle, NetworkDevice, NetworkDevicesHandler, ServerBuilder};
+
+/// Console that answers commands from a script and remembers everything written to it.
+#[derive(Debug, Default, Clone)]
+pub struct FakeConsole {
+    /// Responses keyed by the first line of the command they answer.
+    pub responses: Arc<Mutex<HashMap<String, String>>>,
+    pub delay: Duration,
+    pub sent: Arc<Mutex<Vec<String>>>,
+    /// Commands the line breaks down on, keyed like `responses`.
+    pub failing: Arc<Mutex<HashSet<String>>>,
+    pending: String,
+}
+
+impl FakeConsole {
+    pub fn respond(self, command: &str, response: &str) -> Self {
+        self.set_response(command, response);
+        self
+    }
+
+    /// Changes the response of an already installed console.
+    pub fn set_response(&self, command: &str, response: &str) {
+        self.responses.lock().unwrap().insert(command.to_string(), response.to_string());
+    }
+
+    pub fn fail_on(self, command: &str) -> Self {
+        self.failing.lock().unwrap().insert(command.to_string());
+        self
+    }
+
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn sent(&self) -> Vec<String> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+impl Console for FakeConsole {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        let data = String::from_utf8_lossy(data).to_string();
+        self.sent.lock().unwrap().push(data.clone());
+        self.pending.push_str(&data);
+        Ok(())
+    }
+
+    fn read_available(&mut self) -> std::io::Result<Vec<u8>> {
+        thread::sleep(self.delay);
+        let command = std::mem::take(&mut self.pending);
+        let first_line = command.lines().next().unwrap_or_default().trim().to_string();
+        if self.failing.lock().unwrap().contains(&first_line) {
+            return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, format!("No answer to {}", first_line)));
+        }
+        let response = self.responses.lock().unwrap().get(&first_line).cloned().unwrap_or_default();
+        Ok(format!("{}\n{}", first_line, response).into_bytes())
+    }
+
+    fn read_ready(&mut self, timeout: Duration) -> std::io::Result<Vec<u8>> {
+        if self.pending.is_empty() {
+            thread::sleep(timeout);
+            return Ok(Vec::new());
+        }
+        self.read_available()
+    }
+}
+
+pub fn fake_device(hostname: &str, console: FakeConsole) -> NetworkDevice {
+    NetworkDevice {
+        hostname: hostname.to_string(),
+        s_port: format!("/dev/tty{}", hostname),
+        console: ConsoleHandle::new(console),
+        ..Default::default()
+    }
+}
+
+pub fn test_config() -> ConfigHandler {
+    ConfigHandler {
+        templates_loc: "./src/templates".to_string(),
+        config_templates_loc: "./src/config_templates".to_string(),
+        auth: false,
+        ..Default::default()
+    }
+}
+
+/// Numbers the devices from 1 in the given order.
+pub fn devices_handler(devices: Vec<NetworkDevice>) -> NetworkDevicesHandler {
+    let devices = devices.into_iter()
+        .enumerate()
+        .map(|(index, device)| (index as u32 + 1, device))
+        .collect();
+    NetworkDevicesHandler::new(devices)
+}
+
+pub fn test_app(devices: Vec<NetworkDevice>) -> App<impl ServiceFactory<
+    ServiceRequest,
+    Config = (),
+    Response = ServiceResponse<impl MessageBody>,
+    Error = actix_web::Error,
+    InitError = (),
+>> {
+    test_app_with(devices_handler(devices))
+}
+
+pub fn test_app_with(devices_handler: NetworkDevicesHandler) -> App<impl ServiceFactory<
+    ServiceRequest,
+    Config = (),
+    Response = ServiceResponse<impl MessageBody>,
+    Error = actix_web::Error,
+    InitError = (),
+>> {
+    ServerBuilder::new(test_config(), devices_handler).app().expect("Couldn't build app")
+}
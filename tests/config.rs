@@ -0,0 +1,314 @@
+mod common;
+
+use actix_web::test;
+use serde_json::Value;
+
+use common::{fake_device, test_app, FakeConsole};
+
+const RUNNING_CONFIG: &str = "Building configuration...
+
+Current configuration : 1024 bytes
+!
+version 15.0
+hostname lab-sw1
+!
+vlan 10
+ name users
+!
+interface FastEthernet0/1
+ switchport access vlan 10
+ switchport mode access
+!
+router ospf 1
+ network 10.0.0.0 0.0.0.255 area 0
+!
+line vty 0 4
+ login
+!
+end
+lab-sw1#";
+
+fn console() -> FakeConsole {
+    FakeConsole::default().respond("sh running-config", RUNNING_CONFIG)
+}
+
+#[actix_web::test]
+async fn running_config_keeps_lines() {
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console())])).await;
+
+    let req = test::TestRequest::get().uri("/device/1/config/running?refresh=true&format=text").to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    let text = String::from_utf8(body.to_vec()).unwrap();
+
+    assert!(text.contains("interface FastEthernet0/1\n switchport access vlan 10\n"));
+    assert!(text.ends_with("end"));
+}
+
+#[actix_web::test]
+async fn running_config_is_parsed_into_blocks() {
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console())])).await;
+
+    let req = test::TestRequest::get().uri("/device/1/config/running?refresh=true").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+
+    let global: Vec<&str> = body["config"]["global"].as_array().unwrap().iter().map(|line| line.as_str().unwrap()).collect();
+    assert_eq!(vec!["version 15.0", "hostname lab-sw1"], global);
+
+    let blocks = body["config"]["blocks"].as_array().unwrap();
+    let kinds: Vec<&str> = blocks.iter().map(|block| block["kind"].as_str().unwrap()).collect();
+    assert_eq!(vec!["vlan", "interface", "router", "line"], kinds);
+    assert_eq!("interface FastEthernet0/1", blocks[1]["header"]);
+    assert_eq!("switchport mode access", blocks[1]["children"][1]["line"]);
+}
+
+#[actix_web::test]
+async fn empty_stanzas_and_banners_stay_blocks() {
+    let running_config = "!
+hostname lab-sw1
+!
+interface FastEthernet0/2
+!
+banner motd ^C
+ Authorized access only
+!
+
+^C
+!
+end
+lab-sw1#";
+    let console = FakeConsole::default().respond("sh running-config", running_config);
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console)])).await;
+
+    let req = test::TestRequest::get().uri("/device/1/config/running?refresh=true").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(serde_json::json!(["hostname lab-sw1"]), body["config"]["global"]);
+    let blocks = body["config"]["blocks"].as_array().unwrap();
+    assert_eq!("interface", blocks[0]["kind"]);
+    assert_eq!("interface FastEthernet0/2", blocks[0]["header"]);
+    assert!(blocks[0]["children"].as_array().unwrap().is_empty());
+    assert_eq!("banner", blocks[1]["kind"]);
+    assert_eq!("banner motd ^C", blocks[1]["header"]);
+    let text: Vec<&str> = blocks[1]["children"].as_array().unwrap().iter().map(|line| line["line"].as_str().unwrap()).collect();
+    assert_eq!(vec![" Authorized access only", "!", "", "^C"], text);
+}
+
+const STARTUP_CONFIG: &str = "Using 900 out of 65536 bytes
+!
+version 15.0
+hostname lab-sw1
+!
+interface FastEthernet0/1
+ switchport access vlan 10
+ switchport mode access
+ shutdown
+!
+router ospf 1
+ network 10.0.0.0 0.0.0.255 area 0
+!
+line vty 0 4
+ login
+!
+end
+lab-sw1#";
+
+#[actix_web::test]
+async fn diff_reports_changes_per_section() {
+    let console = console().respond("sh startup-config", STARTUP_CONFIG);
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console)])).await;
+
+    let req = test::TestRequest::get().uri("/device/1/config/diff?refresh=true").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(true, body["unsaved_changes"]);
+    let diff = body["diff"].as_str().unwrap();
+    assert!(diff.starts_with("--- startup-config\n+++ running-config\n"));
+    assert!(diff.contains("@@ vlan 10 @@\n+vlan 10\n+ name users\n"));
+    assert!(diff.contains("@@ interface FastEthernet0/1 @@\n interface FastEthernet0/1\n  switchport access vlan 10\n  switchport mode access\n- shutdown\n"));
+    assert!(!diff.contains("router ospf"));
+}
+
+#[actix_web::test]
+async fn saved_config_has_no_diff() {
+    let console = console().respond("sh startup-config", RUNNING_CONFIG);
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console)])).await;
+
+    let req = test::TestRequest::get().uri("/device/1/config/diff?refresh=true").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(false, body["unsaved_changes"]);
+    assert_eq!("", body["diff"]);
+}
+
+#[actix_web::test]
+async fn save_answers_destination_prompt() {
+    let console = console()
+        .respond("copy running-config startup-config", "Destination filename [startup-config]? ")
+        .respond("", "Building configuration...\n[OK]\nlab-sw1#")
+        .respond("sh startup-config", RUNNING_CONFIG);
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::post().uri("/device/1/config/save").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert!(body["startup_config"].as_str().unwrap().contains("interface FastEthernet0/1"));
+    let sent = console.sent();
+    let copy = sent.iter().position(|line| line.starts_with("copy running-config")).unwrap();
+    assert_eq!("\n", sent[copy + 1]);
+}
+
+#[actix_web::test]
+async fn save_rereads_a_running_config_changed_behind_the_agent() {
+    let console = FakeConsole::default()
+        .respond("sh running-config", STARTUP_CONFIG)
+        .respond("sh startup-config", STARTUP_CONFIG)
+        .respond("copy running-config startup-config", "Building configuration...\n[OK]\nlab-sw1#");
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+    let req = test::TestRequest::get().uri("/device/1/config/diff?refresh=true").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(false, body["unsaved_changes"]);
+
+    // changed on the console, the agent's copy of the running config is stale when saving
+    console.set_response("sh running-config", RUNNING_CONFIG);
+    console.set_response("sh startup-config", RUNNING_CONFIG);
+    let req = test::TestRequest::post().uri("/device/1/config/save").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(false, body["unsaved_changes"]);
+    assert!(body["running_config"].as_str().unwrap().contains("vlan 10"));
+}
+
+#[actix_web::test]
+async fn save_fails_without_ok() {
+    let console = console()
+        .respond("copy running-config startup-config", "%Error opening nvram:startup-config (No such device)");
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console)])).await;
+
+    let req = test::TestRequest::post().uri("/device/1/config/save").to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert!(resp.status().is_client_error());
+}
+
+#[actix_web::test]
+async fn merge_restore_reports_line_errors() {
+    let console = console()
+        .respond("interface FastEthernet0/99", "                    ^\n% Invalid input detected at '^' marker.\nlab-sw1(config)#")
+        .respond("no shutdown", "*Mar  1 00:01:02: %LINK-3-UPDOWN: Interface FastEthernet0/1, changed state to up\nlab-sw1(config-if)#");
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let config = "hostname lab-sw1\n!\ninterface FastEthernet0/1\n no shutdown\n!\ninterface FastEthernet0/99\n!\nend\n";
+    let req = test::TestRequest::post()
+        .uri("/device/1/config/restore")
+        .set_json(serde_json::json!({ "config": config }))
+        .to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!("merge", report["mode"]);
+    assert_eq!(4, report["lines_sent"]);
+    let errors = report["errors"].as_array().unwrap();
+    assert_eq!(1, errors.len());
+    assert_eq!(4, errors[0]["line_number"]);
+    assert_eq!("interface FastEthernet0/99", errors[0]["line"]);
+
+    let sent = console.sent();
+    let conf_t = sent.iter().position(|line| line == "conf t\n").unwrap();
+    assert_eq!("hostname lab-sw1\n", sent[conf_t + 1]);
+    assert_eq!("end\n", sent[conf_t + 5]);
+}
+
+#[actix_web::test]
+async fn replace_restore_uses_configure_replace() {
+    let console = console()
+        .respond("configure replace flash:rpi-restore.cfg force", "Total number of passes: 1\nRollback Done\nlab-sw1#");
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let config = "hostname lab-sw1\n!\ninterface FastEthernet0/1\n switchport mode access\n!\nend\n";
+    let req = test::TestRequest::post()
+        .uri("/device/1/config/restore")
+        .set_json(serde_json::json!({ "config": config, "mode": "replace" }))
+        .to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(0, report["errors"].as_array().unwrap().len());
+    let sent = console.sent();
+    let tclsh = sent.iter().position(|line| line == "tclsh\n").unwrap();
+    let expected = [
+        "tclsh", "set f [open \"flash:rpi-restore.cfg\" w+]", "puts $f {",
+        "hostname lab-sw1", "!", "interface FastEthernet0/1", " switchport mode access", "!", "end",
+        "}", "close $f", "tclquit", "configure replace flash:rpi-restore.cfg force",
+    ];
+    let sent: Vec<&str> = sent[tclsh..tclsh + expected.len()].iter().map(|line| line.trim_end_matches('\n')).collect();
+    assert_eq!(expected.to_vec(), sent);
+}
+
+#[actix_web::test]
+async fn replace_restore_needs_balanced_braces() {
+    let console = console()
+        .respond("configure replace flash:rpi-restore.cfg force", "Total number of passes: 1\nRollback Done\nlab-sw1#");
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    for config in ["banner motd {\n", "banner motd }{\n", "banner motd \\{ }\n", "banner motd x\\\n"] {
+        let req = test::TestRequest::post()
+            .uri("/device/1/config/restore")
+            .set_json(serde_json::json!({ "config": config, "mode": "replace" }))
+            .to_request();
+        assert_eq!(400, test::call_service(&app, req).await.status().as_u16(), "config {:?}", config);
+    }
+    assert!(console.sent().is_empty());
+
+    let req = test::TestRequest::post()
+        .uri("/device/1/config/restore")
+        .set_json(serde_json::json!({ "config": "banner motd {lab {A}}\n", "mode": "replace" }))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+}
+
+#[actix_web::test]
+async fn replace_restore_reports_the_failing_line() {
+    let console = console()
+        .respond("configure replace flash:rpi-restore.cfg force", "interface FastEthernet0/99\n                    ^\n% Invalid input detected at '^' marker.\nRollback aborted after 5 passes\nlab-sw1#");
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let config = "hostname lab-sw1\n!\ninterface FastEthernet0/99\n shutdown\n";
+    let req = test::TestRequest::post()
+        .uri("/device/1/config/restore")
+        .set_json(serde_json::json!({ "config": config, "mode": "replace" }))
+        .to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+
+    let errors = report["errors"].as_array().unwrap();
+    assert_eq!(1, errors.len());
+    assert_eq!(3, errors[0]["line_number"]);
+    assert_eq!("interface FastEthernet0/99", errors[0]["line"]);
+}
+
+#[actix_web::test]
+async fn restore_needs_a_source() {
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console())])).await;
+
+    let req = test::TestRequest::post()
+        .uri("/device/1/config/restore")
+        .set_json(serde_json::json!({ "mode": "merge" }))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_client_error());
+}
+
+#[actix_web::test]
+async fn restore_dry_run_lists_lines() {
+    let console = console();
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::post()
+        .uri("/device/1/config/restore?dry_run=true")
+        .set_json(serde_json::json!({ "config": "hostname lab-sw1\ninterface Vlan1\n ip address 10.0.0.2 255.255.255.0\n", "mode": "merge" }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(serde_json::json!([
+        "en", "terminal length 0", "conf t",
+        "hostname lab-sw1", "interface Vlan1", "ip address 10.0.0.2 255.255.255.0",
+        "end"
+    ]), body["commands"]);
+    assert!(console.sent().is_empty());
+}
@@ -0,0 +1,69 @@
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::sync::Mutex;
+use rpi_client::{app_specific_uuid, ConfigHandler};
+use uuid::Uuid;
+
+// the variables are shared by everything running in this process
+static ENV: Mutex<()> = Mutex::new(());
+
+fn defaults(dir: &tempfile::TempDir) -> ConfigHandler {
+    ConfigHandler {
+        uuid_loc: dir.path().join("agent_uuid").to_str().unwrap().to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn environment_is_checked_when_loading_the_config() {
+    let _env = ENV.lock().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let defaults = defaults(&dir);
+
+    env::set_var("RPI_BIND_ADDRESSES", "127.0.0.1, ::1,");
+    env::set_var("RPI_PORT", "8443");
+    let conf = defaults.init().unwrap();
+    assert_eq!(vec!["127.0.0.1".to_string(), "::1".to_string()], conf.bind_addresses);
+    assert_eq!(8443, conf.port);
+
+    for (var, value) in [("RPI_PORT", "80a"), ("RPI_PORT", "70000"), ("RPI_TERMINAL_BASE_PORT", "-1"), ("RPI_TERMINAL_MODE", "ssh")] {
+        env::set_var(var, value);
+        let Err(error) = defaults.init() else {
+            panic!("{}={} was accepted", var, value);
+        };
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert!(error.to_string().contains(var), "{}", error);
+        env::remove_var(var);
+    }
+    env::remove_var("RPI_BIND_ADDRESSES");
+}
+
+#[test]
+fn agent_uuid_survives_a_restart() {
+    let _env = ENV.lock().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let defaults = defaults(&dir);
+
+    let first = defaults.init().unwrap();
+    assert_eq!(first.uuid, fs::read_to_string(&defaults.uuid_loc).unwrap());
+    let second = defaults.init().unwrap();
+    assert_eq!(first.uuid, second.uuid);
+
+    fs::write(&defaults.uuid_loc, "not a uuid").unwrap();
+    let replaced = defaults.init().unwrap();
+    assert!(Uuid::try_parse(&replaced.uuid).is_ok());
+    assert_eq!(replaced.uuid, fs::read_to_string(&defaults.uuid_loc).unwrap());
+}
+
+#[test]
+fn agent_id_is_derived_from_but_hides_the_machine_id() {
+    let machine_id = Uuid::try_parse("4f0d6e3b2a1c4d5e8f9a0b1c2d3e4f5a").unwrap();
+
+    let agent_id = app_specific_uuid(machine_id);
+
+    assert_eq!(agent_id, app_specific_uuid(machine_id));
+    assert_ne!(machine_id, agent_id);
+    assert_ne!(agent_id, app_specific_uuid(Uuid::new_v4()));
+    assert_eq!(Some(uuid::Version::Random), agent_id.get_version());
+}
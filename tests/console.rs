@@ -0,0 +1,120 @@
+mod common;
+
+use std::time::Duration;
+use futures_util::{SinkExt, StreamExt};
+use rpi_client::{ApiToken, AuditHandler, AuthHandler, ConfigHandler, NetworkDevicesHandler, Role, ServerBuilder};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL;
+use tokio_tungstenite::tungstenite::Message;
+use serde_json::Value;
+
+use common::{devices_handler, fake_device, test_config, FakeConsole};
+
+/// Runs the agent on a free port and returns its address.
+fn serve(devices_handler: NetworkDevicesHandler, auth: AuthHandler) -> String {
+    let conf = ConfigHandler {
+        bind_addresses: vec!["127.0.0.1".to_string()],
+        port: 0,
+        ..test_config()
+    };
+    let (server, addrs) = ServerBuilder::new(conf, devices_handler).auth(auth).build().expect("Failed to bind address");
+    tokio::spawn(server);
+    addrs[0].to_string()
+}
+
+#[tokio::test]
+async fn websocket_is_bridged_to_the_console() {
+    let console = FakeConsole::default().respond("show version", "Cisco IOS Software");
+    let addr = serve(devices_handler(vec![fake_device("lab-sw1", console.clone())]), AuthHandler::disabled());
+    let (mut socket, _) = connect_async(format!("ws://{}/api/v1/devices/1/console", addr)).await.unwrap();
+
+    socket.send(Message::Text("show version\r".to_string())).await.unwrap();
+    let mut printed = String::new();
+    while !printed.contains("Cisco IOS Software") {
+        match tokio::time::timeout(Duration::from_secs(5), socket.next()).await.expect("No console output") {
+            Some(Ok(Message::Binary(data))) => printed.push_str(&String::from_utf8_lossy(&data)),
+            other => panic!("Unexpected frame {:?}", other),
+        }
+    }
+    assert_eq!(vec!["show version\r"], console.sent());
+
+    // the session has the device, so API calls that won't wait are refused meanwhile
+    let client = reqwest::Client::new();
+    let busy = client.post(format!("http://{}/api/v1/devices/1/config/reload?wait=false", addr)).send().await.unwrap();
+    assert_eq!(423, busy.status().as_u16());
+
+    socket.close(None).await.unwrap();
+    let reload = client.post(format!("http://{}/api/v1/devices/1/config/reload", addr)).send().await.unwrap();
+    assert!(reload.status().is_success());
+}
+
+#[tokio::test]
+async fn idle_sessions_end_and_leave_the_device_reread() {
+    let audit_dir = tempfile::tempdir().unwrap();
+    let console = FakeConsole::default()
+        .respond("show version", "Cisco IOS Software")
+        .respond("show clock", "*10:15:00.000 UTC Mon Oct 19 2026")
+        .respond("sh running-config", "hostname core-sw1\ncore-sw1#");
+    let audit = AuditHandler::new(audit_dir.path().to_str().unwrap());
+    let handler = devices_handler(vec![fake_device("lab-sw1", console.clone())])
+        .with_audit(audit.clone())
+        .with_console_idle_timeout(Duration::from_millis(500));
+    let addr = serve(handler, AuthHandler::disabled());
+    let (mut socket, _) = connect_async(format!("ws://{}/api/v1/devices/1/console", addr)).await.unwrap();
+
+    let mut printed = String::new();
+    for (command, expected) in [("show version\r", "Cisco IOS Software"), ("show clock\r", "UTC")] {
+        socket.send(Message::Text(command.to_string())).await.unwrap();
+        while !printed.contains(expected) {
+            match tokio::time::timeout(Duration::from_secs(5), socket.next()).await.expect("No console output") {
+                Some(Ok(Message::Binary(data))) => printed.push_str(&String::from_utf8_lossy(&data)),
+                other => panic!("Unexpected frame {:?}", other),
+            }
+        }
+    }
+    // nothing typed from here on
+    loop {
+        match tokio::time::timeout(Duration::from_secs(5), socket.next()).await.expect("Idle session wasn't closed") {
+            Some(Ok(Message::Binary(data))) => printed.push_str(&String::from_utf8_lossy(&data)),
+            Some(Ok(Message::Close(_))) | None => break,
+            other => panic!("Unexpected frame {:?}", other),
+        }
+    }
+    assert!(printed.contains("without input"));
+
+    let sent = console.sent();
+    let after = &sent[sent.iter().position(|line| line == "show clock\r").unwrap() + 1..];
+    assert_eq!("\u{1a}\n", after[0]);
+    assert!(after.iter().any(|line| line.starts_with("en\n")));
+    let device: Value = serde_json::from_str(&reqwest::get(format!("http://{}/api/v1/devices/1", addr)).await.unwrap().text().await.unwrap()).unwrap();
+    assert_eq!("core-sw1", device["hostname"]);
+
+    // one audit entry per typed line rather than one for the whole session
+    let entries = audit.entries(Some(1), None, 100).unwrap();
+    assert_eq!(vec!["show version".to_string()], entries[0].commands);
+    assert!(entries[0].response.contains("Cisco IOS Software"));
+    assert_eq!(vec!["show clock".to_string()], entries[1].commands);
+    assert!(entries[1].response.contains("UTC"));
+}
+
+#[tokio::test]
+async fn browsers_pass_the_token_as_subprotocol() {
+    let auth = AuthHandler::new(vec![
+        ApiToken::new("instructor", "s3cret", Role::Admin),
+        ApiToken::new("student", "student-token", Role::Viewer),
+    ]);
+    let addr = serve(devices_handler(vec![fake_device("lab-sw1", FakeConsole::default())]), auth);
+    let url = format!("ws://{}/api/v1/devices/1/console", addr);
+
+    let mut request = url.as_str().into_client_request().unwrap();
+    request.headers_mut().insert(SEC_WEBSOCKET_PROTOCOL, "bearer, s3cret".parse().unwrap());
+    let (_socket, response) = connect_async(request).await.expect("Admin token was refused");
+    assert_eq!("bearer", response.headers().get(SEC_WEBSOCKET_PROTOCOL).unwrap());
+
+    // the raw CLI needs an admin token
+    let mut request = url.as_str().into_client_request().unwrap();
+    request.headers_mut().insert(SEC_WEBSOCKET_PROTOCOL, "bearer, student-token".parse().unwrap());
+    assert!(connect_async(request).await.is_err());
+    assert!(connect_async(url.as_str()).await.is_err());
+}
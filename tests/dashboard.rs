@@ -0,0 +1,133 @@
+mod common;
+
+use actix_web::test;
+use actix_web::cookie::SameSite;
+use actix_web::http::header::LOCATION;
+use rpi_client::{ApiToken, AuthHandler, Role, ServerBuilder};
+
+use common::{devices_handler, fake_device, test_app, test_config, FakeConsole};
+
+/// CSRF token the page's forms send back.
+fn csrf_of(page: &str) -> String {
+    let start = page.find(r#"name="csrf" value=""#).expect("Page has no form") + r#"name="csrf" value=""#.len();
+    page[start..].split('"').next().unwrap().to_string()
+}
+
+#[actix_web::test]
+async fn dashboard_lists_devices() {
+    let app = test::init_service(test_app(vec![
+        fake_device("lab-sw1", FakeConsole::default()),
+        fake_device("lab-sw2", FakeConsole::default()),
+    ])).await;
+
+    let req = test::TestRequest::get().uri("/ui").to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+
+    assert!(body.contains("lab-sw1"));
+    assert!(body.contains("/ui/device/2"));
+}
+
+#[actix_web::test]
+async fn dashboard_form_changes_hostname() {
+    let console = FakeConsole::default();
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+    let req = test::TestRequest::get().uri("/ui/device/1").to_request();
+    let page = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+
+    let req = test::TestRequest::post().uri("/ui/device/1/hostname")
+        .set_form([("csrf", csrf_of(&page).as_str()), ("hostname", "core-sw1")])
+        .to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+
+    assert!(body.contains("Hostname changed to core-sw1."));
+    assert!(console.sent().iter().any(|sent| sent.contains("core-sw1")));
+}
+
+#[actix_web::test]
+async fn dashboard_forms_from_other_sites_are_refused() {
+    let console = FakeConsole::default();
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+    let req = test::TestRequest::get().uri("/ui/device/1").to_request();
+    let page = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+
+    let req = test::TestRequest::post().uri("/ui/device/1/hostname")
+        .set_form([("csrf", "guessed"), ("hostname", "pwned")])
+        .to_request();
+    assert_eq!(403, test::call_service(&app, req).await.status().as_u16());
+    let req = test::TestRequest::post().uri("/ui/device/1/vlan/10/delete").to_request();
+    assert!(test::call_service(&app, req).await.status().is_client_error());
+    let req = test::TestRequest::post().uri("/ui/device/1/hostname")
+        .insert_header(("Origin", "https://evil.example"))
+        .set_form([("csrf", csrf_of(&page).as_str()), ("hostname", "pwned")])
+        .to_request();
+    assert_eq!(403, test::call_service(&app, req).await.status().as_u16());
+    assert!(console.sent().is_empty());
+}
+
+#[actix_web::test]
+async fn not_found_page_renders() {
+    let app = test::init_service(test_app(vec![])).await;
+
+    let req = test::TestRequest::get().uri("/nowhere").to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(404, resp.status().as_u16());
+}
+
+#[actix_web::test]
+async fn dashboard_logs_in_with_a_session_cookie() {
+    let console = FakeConsole::default();
+    let auth = AuthHandler::new(vec![
+        ApiToken::new("tutor", "tutor-token", Role::Operator),
+        ApiToken::new("student", "student-token", Role::Viewer),
+    ]);
+    let app = test::init_service(ServerBuilder::new(test_config(), devices_handler(vec![fake_device("lab-sw1", console.clone())]))
+        .auth(auth)
+        .app().unwrap()).await;
+
+    let req = test::TestRequest::get().uri("/ui/device/1").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(303, resp.status().as_u16());
+    assert_eq!("/ui/login", resp.headers().get(LOCATION).unwrap());
+
+    let req = test::TestRequest::post().uri("/ui/login").set_form([("token", "wrong")]).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(401, resp.status().as_u16());
+    assert!(resp.response().cookies().next().is_none());
+
+    let req = test::TestRequest::post().uri("/ui/login").set_form([("token", "tutor-token")]).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(303, resp.status().as_u16());
+    let cookie = resp.response().cookies().next().unwrap().into_owned();
+    assert_eq!(Some(true), cookie.http_only());
+    assert_eq!(Some(SameSite::Strict), cookie.same_site());
+
+    let req = test::TestRequest::get().uri("/ui/device/1").cookie(cookie.clone()).to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+    assert!(body.contains("Logged in as tutor"));
+    let csrf = csrf_of(&body);
+    let req = test::TestRequest::post().uri("/ui/device/1/hostname").cookie(cookie.clone())
+        .set_form([("csrf", csrf.as_str()), ("hostname", "core-sw1")])
+        .to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+    assert!(body.contains("Hostname changed to core-sw1."));
+    // the cookie only opens the dashboard, not the API
+    let req = test::TestRequest::get().uri("/api/v1/devices").cookie(cookie.clone()).to_request();
+    assert_eq!(401, test::call_service(&app, req).await.status().as_u16());
+
+    let req = test::TestRequest::post().uri("/ui/logout").cookie(cookie.clone()).set_form([("csrf", csrf.as_str())]).to_request();
+    assert_eq!(303, test::call_service(&app, req).await.status().as_u16());
+    let req = test::TestRequest::get().uri("/ui").cookie(cookie).to_request();
+    assert_eq!(303, test::call_service(&app, req).await.status().as_u16());
+
+    // viewers may look but not change anything
+    let req = test::TestRequest::post().uri("/ui/login").set_form([("token", "student-token")]).to_request();
+    let cookie = test::call_service(&app, req).await.response().cookies().next().unwrap().into_owned();
+    let req = test::TestRequest::get().uri("/ui/device/1").cookie(cookie.clone()).to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+    let req = test::TestRequest::post().uri("/ui/device/1/hostname").cookie(cookie)
+        .set_form([("csrf", csrf_of(&body).as_str()), ("hostname", "pwned")])
+        .to_request();
+    assert_eq!(403, test::call_service(&app, req).await.status().as_u16());
+    assert!(!console.sent().iter().any(|sent| sent.contains("pwned")));
+}
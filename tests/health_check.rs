@@ -1,12 +1,10 @@
-use rpi_client::run;
-use tokio;
-
+use std::net::SocketAddr;
+use std::collections::HashMap;
+use rpi_client::{ConfigHandler, NetworkDevicesHandler, ServerBuilder};
 
 #[cfg(test)]
 mod tests{
-    use super::*;
-    use actix_web::{test, web, App};
-    use actix_web::error::UrlencodedError::ContentType;
+    use actix_web::{test, App};
 
     #[actix_web::test]
     async fn health_check_works() {
@@ -24,12 +22,12 @@ mod tests{
 
 #[tokio::test]
 async fn health_check_works(){
-    spawn_app();
+    let address = spawn_app();
 
     let client = reqwest::Client::new();
 
     let response = client
-        .get("http://127.0.0.1:8080/status/health")
+        .get(format!("http://{}/status/health", address))
         .send()
         .await
         .expect("Failed to execute request.");
@@ -39,10 +37,39 @@ async fn health_check_works(){
     assert_eq!(Some(0), response.content_length())
 }
 
-fn spawn_app(){
-    let server = rpi_client::run().expect("Failed to bind address");
-    let _ = tokio::spawn(server);
-}
-
+#[tokio::test]
+async fn every_bind_address_is_served() {
+    let conf = ConfigHandler {
+        bind_addresses: vec!["127.0.0.1".to_string(), "::1".to_string()],
+        port: 0,
+        templates_loc: "./src/templates".to_string(),
+        config_templates_loc: "./src/config_templates".to_string(),
+        auth: false,
+        ..Default::default()
+    };
+    let (server, addrs) = ServerBuilder::new(conf, NetworkDevicesHandler::new(HashMap::new())).build().expect("Failed to bind addresses");
+    tokio::spawn(server);
 
+    assert_eq!(2, addrs.len());
+    assert!(addrs.iter().any(|addr| addr.is_ipv4()) && addrs.iter().any(|addr| addr.is_ipv6()));
+    let client = reqwest::Client::new();
+    for addr in addrs {
+        let response = client.get(format!("http://{}/status/health", addr)).send().await.expect("Failed to execute request.");
+        assert!(response.status().is_success());
+    }
+}
 
+fn spawn_app() -> SocketAddr {
+    let conf = ConfigHandler {
+        bind_addresses: vec!["127.0.0.1".to_string()],
+        port: 0,
+        templates_loc: "./src/templates".to_string(),
+        config_templates_loc: "./src/config_templates".to_string(),
+        auth: false,
+        ..Default::default()
+    };
+    let devices_handler = NetworkDevicesHandler::new(HashMap::new());
+    let (server, addrs) = ServerBuilder::new(conf, devices_handler).build().expect("Failed to bind address");
+    tokio::spawn(server);
+    addrs[0]
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::ServiceResponse;
+use actix_web::http::{Method, StatusCode};
+use actix_web::test;
+use serde_json::Value;
+
+use common::{fake_device, test_app, FakeConsole};
+
+/// Fills the path parameters with values that resolve, device 1 exists in the test app.
+fn concrete(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment {
+            "{id}" => "1",
+            segment if segment.starts_with('{') => "x",
+            segment => segment,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Unknown paths end up on the not found page, known paths asked with another method get 405.
+/// Handlers answering 404 themselves don't count as missing.
+async fn is_routed<B: MessageBody>(resp: ServiceResponse<B>) -> bool {
+    match resp.status() {
+        StatusCode::METHOD_NOT_ALLOWED => false,
+        StatusCode::NOT_FOUND => !String::from_utf8_lossy(&test::read_body(resp).await).contains("Nothing is served at"),
+        _ => true,
+    }
+}
+
+#[actix_web::test]
+async fn documented_routes_exist() {
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", FakeConsole::default())])).await;
+
+    let req = test::TestRequest::get().uri("/openapi.json").to_request();
+    let spec: Value = test::call_and_read_body_json(&app, req).await;
+
+    let mut missing = Vec::new();
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in item.as_object().unwrap().keys() {
+            let method = Method::from_bytes(method.to_uppercase().as_bytes()).unwrap();
+            let req = test::TestRequest::default().method(method.clone()).uri(&concrete(path)).to_request();
+            if !is_routed(test::call_service(&app, req).await).await {
+                missing.push(format!("{} {}", method, path));
+            }
+        }
+    }
+    assert!(missing.is_empty(), "documented but not routed: {:?}", missing);
+    let req = test::TestRequest::get().uri("/api/v1/nothing").to_request();
+    assert!(!is_routed(test::call_service(&app, req).await).await);
+    let req = test::TestRequest::delete().uri("/api/v1/devices").to_request();
+    assert!(!is_routed(test::call_service(&app, req).await).await);
+
+    for schema in ["NetworkDevice", "VlanDTO", "InterfaceDTO", "ConfigHandler"] {
+        assert!(spec["components"]["schemas"].get(schema).is_some(), "{} is missing", schema);
+    }
+}
+
+#[actix_web::test]
+async fn swagger_ui_is_served() {
+    let app = test::init_service(test_app(vec![])).await;
+
+    let req = test::TestRequest::get().uri("/swagger-ui/").to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert!(resp.status().is_success());
+}
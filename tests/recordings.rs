@@ -0,0 +1,171 @@
+mod common;
+
+use std::time::Duration;
+use actix_web::test;
+use actix_web::http::header::AUTHORIZATION;
+use rpi_client::{ApiToken, AuthHandler, ConfigHandler, NetworkDevice, RecordingHandler, Role, ServerBuilder};
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use common::{devices_handler, fake_device, test_app_with, test_config, FakeConsole};
+
+/// Header and events of an asciicast v2 recording.
+fn parse_cast(cast: &str) -> (Value, Vec<Value>) {
+    let mut lines = cast.lines().map(|line| serde_json::from_str::<Value>(line).unwrap());
+    let header = lines.next().unwrap();
+    (header, lines.collect())
+}
+
+#[actix_web::test]
+async fn api_jobs_are_recorded_per_device() {
+    let recordings_dir = tempfile::tempdir().unwrap();
+    let console = FakeConsole::default().respond("en", "lab-sw1#");
+    // keyed by serial number, so the rename below keeps the recordings together
+    let device = NetworkDevice { serial_number: "FOC1234X0AB".to_string(), ..fake_device("lab-sw1", console) };
+    let handler = devices_handler(vec![device, fake_device("lab-sw2", FakeConsole::default())])
+        .with_recordings(RecordingHandler::new(recordings_dir.path().to_str().unwrap()));
+    let app = test::init_service(test_app_with(handler)).await;
+
+    let req = test::TestRequest::patch().uri("/api/v1/devices/1").set_json(serde_json::json!({"hostname": "core-sw1"})).to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::get().uri("/api/v1/devices/1").to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::get().uri("/api/v1/devices/1/recordings").to_request();
+    let recordings: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(1, recordings.len());
+    assert_eq!("lab-sw1: PATCH /api/v1/devices/1 by unknown", recordings[0]["title"]);
+    let req = test::TestRequest::get().uri("/api/v1/devices/2/recordings").to_request();
+    let recordings_sw2: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert!(recordings_sw2.is_empty());
+
+    let id = recordings[0]["id"].as_str().unwrap();
+    let req = test::TestRequest::get().uri(&format!("/api/v1/devices/1/recordings/{}", id)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!("application/x-asciicast", resp.headers().get("content-type").unwrap());
+    let (header, events) = parse_cast(std::str::from_utf8(&test::read_body(resp).await).unwrap());
+    assert_eq!(2, header["version"]);
+    assert_eq!(recordings[0]["title"], header["title"]);
+    assert_eq!(serde_json::json!("i"), events[0][1]);
+    assert_eq!(serde_json::json!("en\nconf t\nhostname core-sw1\nend\n"), events[0][2]);
+    assert_eq!(serde_json::json!("o"), events[1][1]);
+    assert!(events[1][2].as_str().unwrap().contains("lab-sw1#"));
+    assert!(events.windows(2).all(|pair| pair[0][0].as_f64() <= pair[1][0].as_f64()));
+
+    let req = test::TestRequest::get().uri("/api/v1/devices/1/recordings/..%2F..%2Fsecret").to_request();
+    assert!(!test::call_service(&app, req).await.status().is_success());
+}
+
+#[actix_web::test]
+async fn recordings_need_a_recording_handler() {
+    let app = test::init_service(test_app_with(devices_handler(vec![fake_device("lab-sw1", FakeConsole::default())]))).await;
+    let req = test::TestRequest::get().uri("/api/v1/devices/1/recordings").to_request();
+    assert!(test::call_service(&app, req).await.status().is_client_error());
+}
+
+#[actix_web::test]
+async fn only_admins_may_replay_recordings() {
+    let recordings_dir = tempfile::tempdir().unwrap();
+    let handler = devices_handler(vec![fake_device("lab-sw1", FakeConsole::default())])
+        .with_recordings(RecordingHandler::new(recordings_dir.path().to_str().unwrap()));
+    let auth = AuthHandler::new(vec![
+        ApiToken::new("instructor", "s3cret", Role::Admin),
+        ApiToken::new("tutor", "tutor-token", Role::Operator),
+    ]);
+    let app = test::init_service(ServerBuilder::new(test_config(), handler).auth(auth).app().unwrap()).await;
+
+    let req = test::TestRequest::post().uri("/api/v1/devices/1/config/reload").insert_header((AUTHORIZATION, "Bearer tutor-token")).to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::get().uri("/api/v1/devices/1/recordings").insert_header((AUTHORIZATION, "Bearer tutor-token")).to_request();
+    assert_eq!(403, test::call_service(&app, req).await.status().as_u16());
+    let req = test::TestRequest::get().uri("/api/v1/devices/1/recordings").insert_header((AUTHORIZATION, "Bearer s3cret")).to_request();
+    let recordings: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    let uri = format!("/api/v1/devices/1/recordings/{}", recordings[0]["id"].as_str().unwrap());
+    let req = test::TestRequest::get().uri(&uri).insert_header((AUTHORIZATION, "Bearer tutor-token")).to_request();
+    assert_eq!(403, test::call_service(&app, req).await.status().as_u16());
+    let req = test::TestRequest::get().uri(&uri).insert_header((AUTHORIZATION, "Bearer s3cret")).to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+}
+
+#[actix_web::test]
+async fn oldest_recordings_are_removed() {
+    let recordings_dir = tempfile::tempdir().unwrap();
+    let device = NetworkDevice { serial_number: "FOC1234X0AB".to_string(), ..fake_device("lab-sw1", FakeConsole::default()) };
+    let handler = devices_handler(vec![device])
+        .with_recordings(RecordingHandler::new(recordings_dir.path().to_str().unwrap()).with_retention(2, u64::MAX));
+    let app = test::init_service(test_app_with(handler)).await;
+
+    for hostname in ["core-sw1", "core-sw2", "core-sw3"] {
+        let req = test::TestRequest::patch().uri("/api/v1/devices/1").set_json(serde_json::json!({"hostname": hostname})).to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+    let req = test::TestRequest::get().uri("/api/v1/devices/1/recordings").to_request();
+    let recordings: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(2, recordings.len());
+    assert!(recordings.iter().all(|recording| !recording["title"].as_str().unwrap().starts_with("lab-sw1:")));
+}
+
+#[actix_web::test]
+async fn long_sessions_prune_older_recordings_when_they_end() {
+    let recordings_dir = tempfile::tempdir().unwrap();
+    let console = FakeConsole::default().respond("sh running-config", &"!\n".repeat(16 * 1024));
+    let device = NetworkDevice { serial_number: "FOC1234X0AB".to_string(), ..fake_device("lab-sw1", console) };
+    let handler = devices_handler(vec![device])
+        .with_recordings(RecordingHandler::new(recordings_dir.path().to_str().unwrap()).with_retention(10, 16 * 1024));
+    let app = test::init_service(test_app_with(handler)).await;
+
+    let req = test::TestRequest::patch().uri("/api/v1/devices/1").set_json(serde_json::json!({"hostname": "core-sw1"})).to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    // starts small, only grows past the limit after its recording was created
+    let req = test::TestRequest::get().uri("/device/1/reload_configs").to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::get().uri("/api/v1/devices/1/recordings").to_request();
+    let recordings: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(1, recordings.len());
+}
+
+#[tokio::test]
+async fn interactive_sessions_are_recorded_as_they_happen() {
+    let recordings_dir = tempfile::tempdir().unwrap();
+    let console = FakeConsole::default().respond("show version", "Cisco IOS Software");
+    let handler = devices_handler(vec![fake_device("lab-sw1", console)])
+        .with_recordings(RecordingHandler::new(recordings_dir.path().to_str().unwrap()));
+    let conf = ConfigHandler {
+        bind_addresses: vec!["127.0.0.1".to_string()],
+        port: 0,
+        terminal: true,
+        terminal_base_port: 0,
+        terminal_mode: serde_json::from_value(Value::from("raw")).unwrap(),
+        ..test_config()
+    };
+    let (server, addrs) = ServerBuilder::new(conf, handler).auth(AuthHandler::disabled()).build().expect("Failed to bind address");
+    tokio::spawn(server);
+    let client = reqwest::Client::new();
+    let api = format!("http://{}/api/v1", addrs[0]);
+    let terminals: Value = serde_json::from_str(&client.get(format!("{}/terminals", api)).send().await.unwrap().text().await.unwrap()).unwrap();
+
+    let mut session = TcpStream::connect(terminals[0]["addresses"][0].as_str().unwrap()).await.unwrap();
+    session.write_all(b"show version\r").await.unwrap();
+    let mut received = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !String::from_utf8_lossy(&received).contains("Cisco IOS Software") {
+        let read = tokio::time::timeout(Duration::from_secs(5), session.read(&mut buffer)).await.unwrap().unwrap();
+        assert!(read > 0);
+        received.extend_from_slice(&buffer[..read]);
+    }
+    drop(session);
+    // queues behind the terminal session, so that one is finished once this answers
+    let reload = client.post(format!("{}/devices/1/config/reload", api)).send().await.unwrap();
+    assert!(reload.status().is_success());
+
+    let recordings: Vec<Value> = serde_json::from_str(&client.get(format!("{}/devices/1/recordings", api)).send().await.unwrap().text().await.unwrap()).unwrap();
+    let terminal = recordings.iter()
+        .find(|recording| recording["title"].as_str().unwrap().contains("terminal"))
+        .expect("Terminal session wasn't recorded");
+    let cast = client.get(format!("{}/devices/1/recordings/{}", api, terminal["id"].as_str().unwrap())).send().await.unwrap().text().await.unwrap();
+    let (_, events) = parse_cast(&cast);
+    assert_eq!(serde_json::json!([ "i", "show version\r"]), serde_json::json!([events[0][1], events[0][2]]));
+    assert!(events.iter().any(|event| event[1] == "o" && event[2].as_str().unwrap().contains("Cisco IOS Software")));
+}
@@ -0,0 +1,77 @@
+use rpi_client::Telnet;
+
+const IAC: u8 = 255;
+const WILL: u8 = 251;
+const DO: u8 = 253;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const COM_PORT: u8 = 44;
+
+#[test]
+fn doubled_iac_is_a_data_byte() {
+    let mut telnet = Telnet::default();
+    let decoded = telnet.decode(&[b'a', IAC, IAC, b'b']);
+    assert_eq!(vec![b'a', IAC, b'b'], decoded.data);
+    assert!(decoded.replies.is_empty());
+
+    assert_eq!(vec![b'a', IAC, IAC, b'b'], Telnet::encode(&[b'a', IAC, b'b']));
+}
+
+#[test]
+fn carriage_returns_drop_the_nul_or_lf_after_them() {
+    let mut telnet = Telnet::default();
+    assert_eq!(b"show\r".to_vec(), telnet.decode(b"show\r\0").data);
+    assert_eq!(b"show\r".to_vec(), telnet.decode(b"show\r\n").data);
+    // split across reads
+    assert_eq!(b"en\r".to_vec(), telnet.decode(b"en\r").data);
+    assert_eq!(b"x".to_vec(), telnet.decode(b"\0x").data);
+    // anything else after it is input
+    assert_eq!(b"a\rb".to_vec(), telnet.decode(b"a\rb").data);
+}
+
+#[test]
+fn subnegotiation_split_across_reads_is_answered_once_complete() {
+    let mut telnet = Telnet::default();
+    assert_eq!(vec![IAC, DO, COM_PORT], telnet.decode(&[IAC, WILL, COM_PORT]).replies);
+
+    // SET-BAUDRATE 115200, cut inside the value and between IAC and SE
+    let first = telnet.decode(&[IAC, SB, COM_PORT, 1, 0, 1]);
+    assert!(first.data.is_empty() && first.replies.is_empty());
+    let second = telnet.decode(&[0xc2, 0, IAC]);
+    assert!(second.data.is_empty() && second.replies.is_empty());
+    let third = telnet.decode(&[SE, b'x']);
+    assert_eq!(vec![IAC, SB, COM_PORT, 101, 0, 0, 0x25, 0x80, IAC, SE], third.replies);
+    assert_eq!(b"x".to_vec(), third.data);
+}
+
+#[test]
+fn unknown_com_port_commands_are_ignored() {
+    let mut telnet = Telnet::default();
+    telnet.decode(&[IAC, WILL, COM_PORT]);
+
+    for command in [13, 156, 200, 254] {
+        let decoded = telnet.decode(&[IAC, SB, COM_PORT, command, IAC, SE, b'x']);
+        assert!(decoded.replies.is_empty(), "command {} was answered", command);
+        assert_eq!(b"x".to_vec(), decoded.data);
+    }
+    // PURGE-DATA is the last one
+    assert_eq!(vec![IAC, SB, COM_PORT, 112, 3, IAC, SE], telnet.decode(&[IAC, SB, COM_PORT, 12, 3, IAC, SE]).replies);
+}
+
+#[test]
+fn oversized_subnegotiations_are_dropped() {
+    let mut telnet = Telnet::default();
+    telnet.decode(&[IAC, WILL, COM_PORT]);
+
+    let mut flood = vec![IAC, SB, COM_PORT, 0];
+    flood.extend([b'a'; 64 * 1024]);
+    let decoded = telnet.decode(&flood);
+    assert!(decoded.data.is_empty() && decoded.replies.is_empty());
+    let decoded = telnet.decode(&[IAC, SE, b'x']);
+    assert!(decoded.replies.is_empty());
+    assert_eq!(b"x".to_vec(), decoded.data);
+
+    // the next one is answered again
+    let decoded = telnet.decode(&[IAC, SB, COM_PORT, 2, 0, IAC, SE]);
+    assert_eq!(vec![IAC, SB, COM_PORT, 102, 8, IAC, SE], decoded.replies);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use actix_web::test;
+use handlebars::Handlebars;
+use rpi_client::{ServerBuilder, TemplateHandler};
+use serde_json::{json, Value};
+
+use common::{devices_handler, fake_device, test_app, test_config, FakeConsole};
+
+fn access_switch_variables() -> Value {
+    json!({
+        "hostname": "access-sw1",
+        "vlans": [{ "number": 10, "name": "users" }],
+        "access_ports": [{ "interface": "FastEthernet0/1", "description": "desk 1", "vlan": 10 }]
+    })
+}
+
+#[actix_web::test]
+async fn lists_and_renders_bundled_template() {
+    let app = test::init_service(test_app(vec![])).await;
+
+    let req = test::TestRequest::get().uri("/templates").to_request();
+    let names: Vec<String> = test::call_and_read_body_json(&app, req).await;
+    assert!(names.contains(&"access-switch".to_string()));
+
+    let req = test::TestRequest::post()
+        .uri("/templates/access-switch/render")
+        .set_json(json!({ "variables": access_switch_variables() }))
+        .to_request();
+    let rendered: Value = test::call_and_read_body_json(&app, req).await;
+    let config = rendered["config"].as_str().unwrap();
+    assert!(config.starts_with("hostname access-sw1\n"));
+    assert!(config.contains("interface FastEthernet0/1\n description desk 1\n switchport mode access\n switchport access vlan 10\n"));
+}
+
+#[actix_web::test]
+async fn missing_variable_fails_rendering() {
+    let app = test::init_service(test_app(vec![])).await;
+
+    let req = test::TestRequest::post()
+        .uri("/templates/access-switch/render")
+        .set_json(json!({ "variables": { "vlans": [], "access_ports": [] } }))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_client_error());
+}
+
+#[actix_web::test]
+async fn templates_can_use_device_facts() {
+    let mut handlebars = Handlebars::new();
+    handlebars.register_template_string("rename", "hostname {{device.hostname}}-{{suffix}}\n").unwrap();
+    let handler = devices_handler(vec![fake_device("lab-sw1", FakeConsole::default())]);
+    let app = ServerBuilder::new(test_config(), handler)
+        .config_templates(TemplateHandler::new(handlebars))
+        .app()
+        .unwrap();
+    let app = test::init_service(app).await;
+
+    let req = test::TestRequest::post()
+        .uri("/templates/rename/render")
+        .set_json(json!({ "variables": { "suffix": "old" }, "device_id": 1 }))
+        .to_request();
+    let rendered: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!("hostname lab-sw1-old\n", rendered["config"]);
+}
+
+#[actix_web::test]
+async fn applying_template_merges_rendered_lines() {
+    let console = FakeConsole::default();
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::post()
+        .uri("/device/1/template/access-switch")
+        .set_json(json!({ "variables": access_switch_variables() }))
+        .to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(0, report["errors"].as_array().unwrap().len());
+    let sent = console.sent();
+    assert!(sent.contains(&"hostname access-sw1\n".to_string()));
+    assert!(sent.contains(&"switchport access vlan 10\n".to_string()));
+}
+
+#[actix_web::test]
+async fn dry_run_template_sends_nothing() {
+    let console = FakeConsole::default();
+    let app = test::init_service(test_app(vec![fake_device("lab-sw1", console.clone())])).await;
+
+    let req = test::TestRequest::post()
+        .uri("/device/1/template/access-switch?dry_run=true")
+        .set_json(json!({ "variables": access_switch_variables() }))
+        .to_request();
+    let dry_run: Value = test::call_and_read_body_json(&app, req).await;
+
+    let commands = dry_run["commands"].as_array().unwrap();
+    assert!(commands.contains(&json!("hostname access-sw1")));
+    assert!(console.sent().is_empty());
+}
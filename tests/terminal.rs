@@ -0,0 +1,124 @@
+mod common;
+
+use std::time::Duration;
+use rpi_client::{ApiToken, AuthHandler, ConfigHandler, NetworkDevicesHandler, Role, ServerBuilder};
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use common::{devices_handler, fake_device, test_config, FakeConsole};
+
+const IAC: u8 = 255;
+const WILL: u8 = 251;
+const DO: u8 = 253;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const COM_PORT: u8 = 44;
+
+/// Runs the agent with terminal ports picked by the OS, returns the HTTP address and the
+/// terminal address of device 1.
+async fn serve(devices_handler: NetworkDevicesHandler, auth: AuthHandler, mode: &str) -> (String, String) {
+    let conf = ConfigHandler {
+        bind_addresses: vec!["127.0.0.1".to_string()],
+        port: 0,
+        terminal: true,
+        terminal_base_port: 0,
+        terminal_mode: serde_json::from_value(Value::from(mode)).unwrap(),
+        ..test_config()
+    };
+    let (server, addrs) = ServerBuilder::new(conf, devices_handler).auth(auth).build().expect("Failed to bind address");
+    tokio::spawn(server);
+
+    let response = reqwest::Client::new().get(format!("http://{}/api/v1/terminals", addrs[0]))
+        .bearer_auth("s3cret")
+        .send().await.unwrap();
+    let terminals: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(mode, terminals[0]["mode"]);
+    (addrs[0].to_string(), terminals[0]["addresses"][0].as_str().unwrap().to_string())
+}
+
+/// Reads until `expected` shows up in what the terminal sent, returns all of it.
+async fn read_until(stream: &mut TcpStream, expected: &[u8]) -> Vec<u8> {
+    let mut received = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !received.windows(expected.len()).any(|window| window == expected) {
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buffer)).await
+            .unwrap_or_else(|_| panic!("Didn't receive {:?}, only {:?}", String::from_utf8_lossy(expected), String::from_utf8_lossy(&received)))
+            .unwrap();
+        assert!(read > 0, "Terminal hung up after {:?}", String::from_utf8_lossy(&received));
+        received.extend_from_slice(&buffer[..read]);
+    }
+    received
+}
+
+#[tokio::test]
+async fn raw_port_is_an_exclusive_console_session() {
+    let console = FakeConsole::default().respond("show version", "Cisco IOS Software");
+    let (http, terminal) = serve(devices_handler(vec![fake_device("lab-sw1", console.clone())]), AuthHandler::disabled(), "raw").await;
+
+    let mut session = TcpStream::connect(&terminal).await.unwrap();
+    session.write_all(b"show version\r").await.unwrap();
+    read_until(&mut session, b"Cisco IOS Software").await;
+    assert_eq!(vec!["show version\r"], console.sent());
+
+    let mut second = TcpStream::connect(&terminal).await.unwrap();
+    read_until(&mut second, b"in use by another terminal session").await;
+    let client = reqwest::Client::new();
+    let busy = client.post(format!("http://{}/api/v1/devices/1/config/reload?wait=false", http)).send().await.unwrap();
+    assert_eq!(423, busy.status().as_u16());
+
+    drop(session);
+    let reload = client.post(format!("http://{}/api/v1/devices/1/config/reload", http)).send().await.unwrap();
+    assert!(reload.status().is_success());
+}
+
+#[tokio::test]
+async fn telnet_port_asks_for_an_admin_token_and_speaks_rfc_2217() {
+    let auth = AuthHandler::new(vec![
+        ApiToken::new("instructor", "s3cret", Role::Admin),
+        ApiToken::new("student", "student-token", Role::Viewer),
+    ]);
+    let console = FakeConsole::default().respond("show version", "Cisco IOS Software");
+    let (_http, terminal) = serve(devices_handler(vec![fake_device("lab-sw1", console.clone())]), auth, "telnet").await;
+
+    let mut student = TcpStream::connect(&terminal).await.unwrap();
+    read_until(&mut student, b"Token: ").await;
+    student.write_all(b"student-token\r\n").await.unwrap();
+    read_until(&mut student, b"Access denied.").await;
+
+    let mut session = TcpStream::connect(&terminal).await.unwrap();
+    let greeting = read_until(&mut session, b"Token: ").await;
+    assert!(greeting.starts_with(&[IAC, WILL, 1]), "Agent doesn't take over the echo");
+    session.write_all(&[IAC, WILL, COM_PORT]).await.unwrap();
+    read_until(&mut session, &[IAC, DO, COM_PORT]).await;
+    session.write_all(b"s3cret\r\0").await.unwrap();
+
+    // asking for 115200 baud is answered with the 9600 the agent keeps using
+    session.write_all(&[IAC, SB, COM_PORT, 1, 0, 1, 0xc2, 0, IAC, SE]).await.unwrap();
+    read_until(&mut session, &[IAC, SB, COM_PORT, 101, 0, 0, 0x25, 0x80, IAC, SE]).await;
+
+    session.write_all(b"show version\r\0").await.unwrap();
+    read_until(&mut session, b"Cisco IOS Software").await;
+    assert_eq!(vec!["show version\r"], console.sent());
+}
+
+#[tokio::test]
+async fn addresses_failing_to_log_in_are_locked_out() {
+    let auth = AuthHandler::new(vec![ApiToken::new("instructor", "s3cret", Role::Admin)]);
+    let (_http, terminal) = serve(devices_handler(vec![fake_device("lab-sw1", FakeConsole::default())]), auth, "raw").await;
+
+    for _ in 0..5 {
+        let mut guess = TcpStream::connect(&terminal).await.unwrap();
+        read_until(&mut guess, b"Token: ").await;
+        guess.write_all(b"guess\r\n").await.unwrap();
+        read_until(&mut guess, b"Access denied.").await;
+    }
+    // even the right token, the address has used up its attempts
+    let mut session = TcpStream::connect(&terminal).await.unwrap();
+    read_until(&mut session, b"Too many failed logins").await;
+}
+
+#[tokio::test]
+async fn terminal_ports_listen_on_loopback_by_default() {
+    assert_eq!(vec!["127.0.0.1".to_string()], ConfigHandler::default().terminal_bind_addresses);
+}
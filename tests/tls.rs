@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::path::Path;
+use rcgen::{BasicConstraints, Certificate, CertificateParams, ExtendedKeyUsagePurpose, IsCa};
+use rpi_client::{ApiToken, AuditHandler, AuthHandler, ConfigHandler, NetworkDevicesHandler, Role, ServerBuilder};
+
+fn tls_config(dir: &Path) -> ConfigHandler {
+    ConfigHandler {
+        bind_addresses: vec!["127.0.0.1".to_string()],
+        port: 0,
+        templates_loc: "./src/templates".to_string(),
+        config_templates_loc: "./src/config_templates".to_string(),
+        auth: false,
+        tls: true,
+        tls_cert_loc: dir.join("tls/cert.pem").to_str().unwrap().to_string(),
+        tls_key_loc: dir.join("tls/key.pem").to_str().unwrap().to_string(),
+        ..Default::default()
+    }
+}
+
+fn ca() -> Certificate {
+    let mut params = CertificateParams::default();
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    Certificate::from_params(params).unwrap()
+}
+
+/// Client identity in PEM, certificate followed by its key, signed by `ca`.
+fn client_identity(ca: &Certificate) -> reqwest::Identity {
+    let mut params = CertificateParams::new(vec!["controller".to_string()]);
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+    let client = Certificate::from_params(params).unwrap();
+    let cert = client.serialize_pem_with_signer(ca).unwrap();
+    reqwest::Identity::from_pkcs8_pem(cert.as_bytes(), client.serialize_private_key_pem().as_bytes()).unwrap()
+}
+
+#[tokio::test]
+async fn serves_https_with_generated_certificate() {
+    let tls_dir = tempfile::tempdir().unwrap();
+    let conf = tls_config(tls_dir.path());
+    let (server, addrs) = ServerBuilder::new(conf.clone(), NetworkDevicesHandler::new(HashMap::new()))
+        .build().expect("Failed to bind address");
+    tokio::spawn(server);
+
+    // names the IP address as such, so clients trusting it can verify it
+    let cert = std::fs::read_to_string(&conf.tls_cert_loc).unwrap();
+    assert!(cert.starts_with("-----BEGIN CERTIFICATE-----"));
+    let client = reqwest::Client::builder()
+        .add_root_certificate(reqwest::Certificate::from_pem(cert.as_bytes()).unwrap())
+        .build().unwrap();
+    let response = client.get(format!("https://{}/status/health", addrs[0]))
+        .send().await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+
+    // the generated certificate is kept for the next start
+    let (_server, _) = ServerBuilder::new(conf.clone(), NetworkDevicesHandler::new(HashMap::new())).build().unwrap();
+    assert_eq!(cert, std::fs::read_to_string(&conf.tls_cert_loc).unwrap());
+}
+
+#[tokio::test]
+async fn half_a_certificate_pair_is_refused() {
+    let tls_dir = tempfile::tempdir().unwrap();
+    let conf = tls_config(tls_dir.path());
+    std::fs::create_dir_all(tls_dir.path().join("tls")).unwrap();
+
+    std::fs::write(&conf.tls_key_loc, "existing key").unwrap();
+    assert!(ServerBuilder::new(conf.clone(), NetworkDevicesHandler::new(HashMap::new())).build().is_err());
+    assert_eq!("existing key", std::fs::read_to_string(&conf.tls_key_loc).unwrap());
+    assert!(!Path::new(&conf.tls_cert_loc).exists());
+
+    std::fs::remove_file(&conf.tls_key_loc).unwrap();
+    std::fs::write(&conf.tls_cert_loc, "existing cert").unwrap();
+    assert!(ServerBuilder::new(conf.clone(), NetworkDevicesHandler::new(HashMap::new())).build().is_err());
+    assert_eq!("existing cert", std::fs::read_to_string(&conf.tls_cert_loc).unwrap());
+    assert!(!Path::new(&conf.tls_key_loc).exists());
+}
+
+#[tokio::test]
+async fn client_certificates_from_the_ca_authenticate_as_admin() {
+    let tls_dir = tempfile::tempdir().unwrap();
+    let client_ca = ca();
+    let ca_loc = tls_dir.path().join("client-ca.pem");
+    std::fs::write(&ca_loc, client_ca.serialize_pem().unwrap()).unwrap();
+    let conf = ConfigHandler {
+        auth: true,
+        tls_client_ca_loc: Some(ca_loc.to_str().unwrap().to_string()),
+        ..tls_config(tls_dir.path())
+    };
+    let devices_handler = NetworkDevicesHandler::new(HashMap::new())
+        .with_audit(AuditHandler::new(tls_dir.path().join("audit").to_str().unwrap()));
+    let (server, addrs) = ServerBuilder::new(conf, devices_handler)
+        .auth(AuthHandler::new(vec![ApiToken::new("instructor", "s3cret", Role::Admin)]))
+        .build().expect("Failed to bind address");
+    tokio::spawn(server);
+    let audit = format!("https://{}/api/v1/audit", addrs[0]);
+
+    let controller = reqwest::Client::builder().danger_accept_invalid_certs(true).identity(client_identity(&client_ca)).build().unwrap();
+    let response = controller.get(&audit).send().await.expect("Client certificate was refused");
+    assert_eq!(200, response.status().as_u16());
+
+    let anonymous = reqwest::Client::builder().danger_accept_invalid_certs(true).build().unwrap();
+    assert_eq!(401, anonymous.get(&audit).send().await.unwrap().status().as_u16());
+
+    // signed by a CA the agent doesn't know, the handshake fails
+    let stranger = reqwest::Client::builder().danger_accept_invalid_certs(true).identity(client_identity(&ca())).build().unwrap();
+    assert!(stranger.get(&audit).send().await.is_err());
+}